@@ -0,0 +1,100 @@
+//! Compares drawing a large, plot-like mesh with `u32` indices (desktop OpenGL)
+//! and with `split_to_u16` (Metal):
+//!
+//! ```sh
+//! cargo bench --features headless --bench large_mesh
+//! ```
+//!
+//! Nothing is drawn on a GPU, this measures the work done by the painter:
+//! allocations, buffer uploads and draw calls.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::RefCell,
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use egui_miniquad::headless::{Command, HeadlessPlatform, RecordingBackend};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const POINTS: usize = 200_000;
+const FRAMES: usize = 20;
+
+fn plot(ui: &mut egui::Ui) {
+    let rect = ui.max_rect();
+    let points = (0..POINTS)
+        .map(|i| {
+            let t = i as f32 / POINTS as f32;
+            egui::pos2(
+                rect.left() + t * rect.width(),
+                rect.center().y + (t * 500.0).sin() * rect.height() * 0.4,
+            )
+        })
+        .collect::<Vec<_>>();
+    ui.painter().add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.0, egui::Color32::RED),
+    ));
+}
+
+fn bench(name: &str, backend: miniquad::Backend) {
+    let mut backend = RecordingBackend::new(backend);
+    let platform = Rc::new(RefCell::new(HeadlessPlatform::new(1920.0, 1080.0)));
+    let mut egui_mq = egui_miniquad::EguiMq::with_platform(&mut backend, platform);
+
+    let mut allocations = 0;
+    let mut allocated_bytes = 0;
+    let mut seconds = 0.0;
+    let mut commands = Vec::new();
+    for _ in 0..FRAMES {
+        egui_mq.run(&mut backend, |_mq_ctx, egui_ctx| {
+            egui::CentralPanel::default().show(egui_ctx, plot);
+        });
+        backend.take_commands();
+
+        let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+        let allocated_bytes_before = ALLOCATED_BYTES.load(Ordering::Relaxed);
+        let start = Instant::now();
+        egui_mq.draw(&mut backend);
+        seconds += start.elapsed().as_secs_f64();
+        allocations += ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+        allocated_bytes += ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes_before;
+        commands = backend.take_commands();
+    }
+
+    let count = |f: fn(&Command) -> bool| commands.iter().filter(|c| f(c)).count();
+    println!(
+        "{name}: {:.2} ms/frame, {} allocations ({} KiB)/frame, {} draw calls, {} buffer updates",
+        seconds * 1000.0 / FRAMES as f64,
+        allocations / FRAMES,
+        allocated_bytes / FRAMES / 1024,
+        count(|c| matches!(c, Command::Draw { .. })),
+        count(|c| matches!(c, Command::BufferUpdate(_))),
+    );
+}
+
+fn main() {
+    bench("u32 indices (OpenGL)", miniquad::Backend::OpenGl);
+    bench("split_to_u16 (Metal)", miniquad::Backend::Metal);
+}
@@ -0,0 +1,21 @@
+// localStorage for egui-miniquad's `persistence` feature.
+// Load after gl.js and sapp_jsutils.js.
+
+function egui_miniquad_storage_register_plugin(importObject) {
+    importObject.env.egui_miniquad_storage_get = function (key_js) {
+        var value = window.localStorage.getItem(get_js_object(key_js));
+        if (value === null) {
+            return -1;
+        }
+        return js_object(value);
+    }
+    importObject.env.egui_miniquad_storage_set = function (key_js, value_js) {
+        window.localStorage.setItem(get_js_object(key_js), get_js_object(value_js));
+    }
+}
+
+miniquad_add_plugin({
+    register_plugin: egui_miniquad_storage_register_plugin,
+    name: "egui_miniquad_storage",
+    version: "0.1.0"
+});
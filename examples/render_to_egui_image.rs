@@ -171,6 +171,8 @@ impl mq::EventHandler for Stage {
         // create egui TextureId from Miniquad GL texture Id
         let raw_id = match unsafe { self.mq_ctx.texture_raw_id(mq_texture) } {
             mq::RawId::OpenGl(id) => id as u64,
+            #[cfg(target_vendor = "apple")]
+            mq::RawId::Metal(id) => id as u64,
         };
         let egui_texture_id = egui::TextureId::User(raw_id);
 
@@ -222,8 +224,8 @@ impl mq::EventHandler for Stage {
         self.egui_mq.char_event(character);
     }
 
-    fn key_down_event(&mut self, keycode: mq::KeyCode, keymods: mq::KeyMods, _repeat: bool) {
-        self.egui_mq.key_down_event(keycode, keymods);
+    fn key_down_event(&mut self, keycode: mq::KeyCode, keymods: mq::KeyMods, repeat: bool) {
+        self.egui_mq.key_down_event(keycode, keymods, repeat);
     }
 
     fn key_up_event(&mut self, keycode: mq::KeyCode, keymods: mq::KeyMods) {
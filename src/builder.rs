@@ -0,0 +1,254 @@
+use miniquad as mq;
+
+use crate::{
+    painter, ClipboardProvider, EguiMq, MiniquadPlatform, PainterOptions, Platform, SharedPainter,
+    SystemClipboard, WheelOptions,
+};
+
+/// Configures an [`EguiMq`] before the first frame.
+///
+/// ```
+/// # fn f(mq_ctx: &mut dyn miniquad::RenderingBackend) {
+/// let egui_ctx = egui::Context::default();
+/// let egui_mq = egui_miniquad::EguiMqBuilder::new()
+///     .context(egui_ctx.clone())
+///     .pixels_per_point(1.5)
+///     .style(egui::Style {
+///         visuals: egui::Visuals::light(),
+///         ..Default::default()
+///     })
+///     .build(mq_ctx);
+/// # }
+/// ```
+#[derive(Default)]
+pub struct EguiMqBuilder {
+    egui_ctx: Option<egui::Context>,
+    pixels_per_point: Option<f32>,
+    fonts: Option<egui::FontDefinitions>,
+    style: Option<egui::Style>,
+    painter_options: PainterOptions,
+    wheel_options: WheelOptions,
+    shared_painter: Option<SharedPainter>,
+    platform: Option<Box<dyn Platform>>,
+    clipboard: Option<Box<dyn ClipboardProvider>>,
+    #[cfg(feature = "persistence")]
+    storage: Option<Box<dyn crate::persistence::Storage>>,
+}
+
+impl EguiMqBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use this context instead of a new one, e.g. to share it with other parts of your app.
+    pub fn context(mut self, egui_ctx: egui::Context) -> Self {
+        self.egui_ctx = Some(egui_ctx);
+        self
+    }
+
+    /// Start with this many physical pixels per point instead of the native DPI scale.
+    ///
+    /// See [`egui::Context::set_pixels_per_point`].
+    pub fn pixels_per_point(mut self, pixels_per_point: f32) -> Self {
+        self.pixels_per_point = Some(pixels_per_point);
+        self
+    }
+
+    /// See [`egui::Context::set_fonts`].
+    pub fn fonts(mut self, fonts: egui::FontDefinitions) -> Self {
+        self.fonts = Some(fonts);
+        self
+    }
+
+    /// See [`egui::Context::set_style`].
+    pub fn style(mut self, style: egui::Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    pub fn painter_options(mut self, painter_options: PainterOptions) -> Self {
+        self.painter_options = painter_options;
+        self
+    }
+
+    /// See [`EguiMq::set_wheel_options`].
+    pub fn wheel_options(mut self, wheel_options: WheelOptions) -> Self {
+        self.wheel_options = wheel_options;
+        self
+    }
+
+    /// Draw with the same pipeline and buffers as other [`EguiMq`]s.
+    ///
+    /// ```
+    /// # fn f(mq_ctx: &mut dyn miniquad::RenderingBackend) {
+    /// use egui_miniquad::{EguiMqBuilder, SharedPainter};
+    ///
+    /// let painter = SharedPainter::new(mq_ctx);
+    /// let hud = EguiMqBuilder::new().shared_painter(painter.clone()).build(mq_ctx);
+    /// let debug_overlay = EguiMqBuilder::new().shared_painter(painter).build(mq_ctx);
+    /// # }
+    /// ```
+    pub fn shared_painter(mut self, shared_painter: SharedPainter) -> Self {
+        self.shared_painter = Some(shared_painter);
+        self
+    }
+
+    /// See [`EguiMq::with_platform`]. Defaults to [`MiniquadPlatform`].
+    pub fn platform(mut self, platform: impl Platform + 'static) -> Self {
+        self.platform = Some(Box::new(platform));
+        self
+    }
+
+    /// See [`EguiMq::with_clipboard`]. Defaults to [`SystemClipboard`].
+    pub fn clipboard(mut self, clipboard: impl ClipboardProvider + 'static) -> Self {
+        self.clipboard = Some(Box::new(clipboard));
+        self
+    }
+
+    /// See [`EguiMq::new_with_storage`].
+    #[cfg(feature = "persistence")]
+    pub fn storage(mut self, storage: impl crate::persistence::Storage + 'static) -> Self {
+        self.storage = Some(Box::new(storage));
+        self
+    }
+
+    pub fn build(self, mq_ctx: &mut dyn mq::RenderingBackend) -> EguiMq {
+        let Self {
+            egui_ctx,
+            pixels_per_point,
+            fonts,
+            style,
+            painter_options,
+            wheel_options,
+            shared_painter,
+            platform,
+            clipboard,
+            #[cfg(feature = "persistence")]
+            storage,
+        } = self;
+
+        let platform = platform.unwrap_or_else(|| Box::new(MiniquadPlatform::new()));
+        let clipboard = clipboard.unwrap_or_else(|| Box::new(SystemClipboard::new()));
+        let shared_painter = shared_painter.unwrap_or_else(|| SharedPainter::new(mq_ctx));
+        let egui_ctx = egui_ctx.unwrap_or_default();
+        let native_dpi_scale = platform.dpi_scale();
+        let now = platform.now();
+
+        #[cfg(feature = "persistence")]
+        if let Some(memory) = storage.as_deref().and_then(crate::persistence::load_memory) {
+            egui_ctx.memory_mut(|m| *m = memory);
+        }
+        if let Some(fonts) = fonts {
+            egui_ctx.set_fonts(fonts);
+        }
+        if let Some(style) = style {
+            egui_ctx.set_style(style);
+        }
+        if let Some(pixels_per_point) = pixels_per_point {
+            // egui multiplies the zoom by the native scale it gets on the first `run`:
+            egui_ctx.set_zoom_factor(pixels_per_point / native_dpi_scale);
+        }
+
+        let mut egui_input = egui::RawInput::default();
+        egui_input
+            .viewports
+            .entry(egui_input.viewport_id)
+            .or_default()
+            .native_pixels_per_point = Some(native_dpi_scale);
+
+        EguiMq {
+            native_dpi_scale,
+            pixels_per_point: pixels_per_point.unwrap_or(native_dpi_scale),
+            egui_ctx,
+            painter: painter::Painter::new(shared_painter, painter_options),
+            egui_input,
+            #[cfg(feature = "persistence")]
+            last_save_time: now,
+            platform,
+            clipboard,
+            on_clipboard_error: Box::new(|err| eprintln!("Copy image error: {}", err)),
+            shapes: None,
+            textures_delta: Default::default(),
+            touch_emulates_pointer: false,
+            primary_touch: None,
+            ime: None,
+            wheel_options,
+            on_unhandled_key: None,
+            viewport: None,
+            target_size: None,
+            pointer_in_viewport: true,
+            held_buttons: 0,
+            touches_in_viewport: Vec::new(),
+            // Run right away:
+            repaint_delay: std::time::Duration::ZERO,
+            last_run_time: now,
+            cursor_visible: true,
+            wants_pointer_input: false,
+            wants_keyboard_input: false,
+            pointer_over_area: false,
+            close_requested: false,
+            close_allowed: false,
+            viewport_info_changed: false,
+            unsupported_commands: Vec::new(),
+            #[cfg(feature = "persistence")]
+            storage,
+            #[cfg(feature = "persistence")]
+            auto_save_interval: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{run_frame, HeadlessPlatform, RecordingBackend};
+
+    fn build(builder: EguiMqBuilder, dpi_scale: f32) -> (RecordingBackend, EguiMq) {
+        let mut backend = RecordingBackend::new(mq::Backend::OpenGl);
+        let mut platform = HeadlessPlatform::new(800.0, 600.0);
+        platform.dpi_scale = dpi_scale;
+        let egui_mq = builder.platform(platform).build(&mut backend);
+        (backend, egui_mq)
+    }
+
+    #[test]
+    fn settings_are_applied() {
+        let egui_ctx = egui::Context::default();
+        let mut fonts = egui::FontDefinitions::default();
+        let family = egui::FontFamily::Name("Test".into());
+        fonts
+            .families
+            .insert(family.clone(), vec!["Hack".to_owned()]);
+        let style = egui::Style {
+            visuals: egui::Visuals::light(),
+            ..Default::default()
+        };
+        let builder = EguiMqBuilder::new()
+            .context(egui_ctx.clone())
+            .fonts(fonts)
+            .style(style)
+            .pixels_per_point(1.5);
+        let (mut backend, mut egui_mq) = build(builder, 2.0);
+
+        assert_eq!(egui_mq.egui_ctx(), &egui_ctx);
+        assert!(!egui_ctx.style().visuals.dark_mode);
+        for _ in 0..2 {
+            let mut pixels_per_point = 0.0;
+            run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+                pixels_per_point = egui_ctx.pixels_per_point();
+            });
+            assert_eq!(pixels_per_point, 1.5);
+        }
+        assert!(egui_ctx.fonts(|f| f.families().contains(&family)));
+    }
+
+    #[test]
+    fn native_dpi_scale_is_the_default() {
+        let (mut backend, mut egui_mq) = build(EguiMqBuilder::new(), 2.0);
+        let mut pixels_per_point = 0.0;
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            pixels_per_point = egui_ctx.pixels_per_point();
+        });
+        assert_eq!(pixels_per_point, 2.0);
+    }
+}
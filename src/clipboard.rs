@@ -0,0 +1,152 @@
+#[cfg(target_os = "macos")] // https://github.com/not-fl3/miniquad/issues/172
+use copypasta::ClipboardProvider as _;
+
+/// Where copied text goes, and where pasted text comes from.
+///
+/// [`crate::EguiMq`] uses [`SystemClipboard`] unless given another one with
+/// [`crate::EguiMq::with_clipboard`].
+pub trait ClipboardProvider {
+    /// The current text in the clipboard, if any.
+    fn get(&mut self) -> Option<String>;
+
+    fn set(&mut self, text: &str);
+
+    /// Put an image in the clipboard, for [`egui::OutputCommand::CopyImage`].
+    ///
+    /// Not supported unless implemented, which includes [`SystemClipboard`].
+    /// To copy images, implement this with a crate that can, e.g. `arboard`.
+    fn set_image(&mut self, _image: &egui::ColorImage) -> Result<(), ClipboardError> {
+        Err(ClipboardError::Unsupported)
+    }
+}
+
+/// Why [`ClipboardProvider::set_image`] failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClipboardError {
+    /// The clipboard can't hold images.
+    Unsupported,
+    Other(String),
+}
+
+impl std::fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsupported => write!(f, "The clipboard does not support images"),
+            Self::Other(err) => write!(f, "Clipboard error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {}
+
+/// Shared ownership, so the clipboard can be inspected while [`crate::EguiMq`] uses it.
+impl<C: ClipboardProvider> ClipboardProvider for std::rc::Rc<std::cell::RefCell<C>> {
+    fn get(&mut self) -> Option<String> {
+        self.borrow_mut().get()
+    }
+
+    fn set(&mut self, text: &str) {
+        self.borrow_mut().set(text);
+    }
+
+    fn set_image(&mut self, image: &egui::ColorImage) -> Result<(), ClipboardError> {
+        self.borrow_mut().set_image(image)
+    }
+}
+
+/// The clipboard of the operating system.
+///
+/// Uses [`miniquad::window::clipboard_get`] and [`miniquad::window::clipboard_set`],
+/// except on macOS where it uses `copypasta`.
+///
+/// Only supports text, since neither of those can copy images: its
+/// [`ClipboardProvider::set_image`] always fails with [`ClipboardError::Unsupported`].
+pub struct SystemClipboard {
+    #[cfg(target_os = "macos")]
+    clipboard: Option<copypasta::ClipboardContext>,
+}
+
+impl SystemClipboard {
+    #[allow(clippy::new_without_default)] // there is only one system clipboard
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_os = "macos")]
+            clipboard: init_clipboard(),
+        }
+    }
+}
+
+impl ClipboardProvider for SystemClipboard {
+    #[cfg(not(target_os = "macos"))]
+    fn get(&mut self) -> Option<String> {
+        miniquad::window::clipboard_get()
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn set(&mut self, text: &str) {
+        miniquad::window::clipboard_set(text);
+    }
+
+    #[cfg(target_os = "macos")]
+    fn get(&mut self) -> Option<String> {
+        if let Some(clipboard) = &mut self.clipboard {
+            match clipboard.get_contents() {
+                Ok(contents) => Some(contents),
+                Err(err) => {
+                    eprintln!("Paste error: {}", err);
+                    None
+                }
+            }
+        } else {
+            None
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn set(&mut self, text: &str) {
+        if let Some(clipboard) = &mut self.clipboard {
+            if let Err(err) = clipboard.set_contents(text.to_owned()) {
+                eprintln!("Copy/Cut error: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn init_clipboard() -> Option<copypasta::ClipboardContext> {
+    match copypasta::ClipboardContext::new() {
+        Ok(clipboard) => Some(clipboard),
+        Err(err) => {
+            eprintln!("Failed to initialize clipboard: {}", err);
+            None
+        }
+    }
+}
+
+/// A clipboard that only lives in memory, e.g. for testing copy and paste.
+///
+/// Wrap it in an `Rc<RefCell<_>>` to look at it while [`crate::EguiMq`] uses it.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryClipboard {
+    /// Set by [`ClipboardProvider::set`], which clears [`Self::image`].
+    pub text: Option<String>,
+    /// Set by [`ClipboardProvider::set_image`], which clears [`Self::text`].
+    pub image: Option<egui::ColorImage>,
+}
+
+impl ClipboardProvider for InMemoryClipboard {
+    fn get(&mut self) -> Option<String> {
+        self.text.clone()
+    }
+
+    fn set(&mut self, text: &str) {
+        self.text = Some(text.to_owned());
+        self.image = None;
+    }
+
+    fn set_image(&mut self, image: &egui::ColorImage) -> Result<(), ClipboardError> {
+        self.text = None;
+        self.image = Some(image.clone());
+        Ok(())
+    }
+}
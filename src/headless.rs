@@ -0,0 +1,717 @@
+//! Run [`crate::EguiMq`] without a window or GPU, e.g. in `cargo test` on a CI machine.
+//!
+//! [`HeadlessPlatform`] stands in for the miniquad window and
+//! [`RecordingBackend`] records what the painter asks the GPU to do.
+//!
+//! ```
+//! use std::{cell::RefCell, rc::Rc};
+//! use egui_miniquad::headless::{Command, HeadlessPlatform, RecordingBackend};
+//!
+//! let mut backend = RecordingBackend::new(miniquad::Backend::OpenGl);
+//! let platform = Rc::new(RefCell::new(HeadlessPlatform::new(800.0, 600.0)));
+//! let mut egui_mq = egui_miniquad::EguiMq::with_platform(&mut backend, platform.clone());
+//!
+//! egui_mq.run(&mut backend, |_mq_ctx, egui_ctx| {
+//!     egui::CentralPanel::default().show(egui_ctx, |ui| {
+//!         ui.label("Hello World!");
+//!     });
+//! });
+//! egui_mq.draw(&mut backend);
+//!
+//! let commands = backend.take_commands();
+//! assert!(commands.iter().any(|command| matches!(command, Command::Draw { .. })));
+//! assert!(platform.borrow().mouse_shown);
+//! ```
+
+use std::{cell::RefCell, collections::HashMap};
+
+use miniquad::{
+    Backend, BufferId, BufferLayout, BufferSource, BufferType, BufferUsage, ContextInfo,
+    FilterMode, MipmapFilterMode, PassAction, Pipeline, PipelineParams, RawId, RenderPass,
+    RenderingBackend, ShaderError, ShaderId, ShaderMeta, ShaderSource, TextureAccess, TextureId,
+    TextureParams, TextureSource, TextureWrap, VertexAttribute,
+};
+
+use crate::Platform;
+
+/// A [`Platform`] without a window.
+///
+/// Set the fields to simulate the window, and read them back to see what egui did to it.
+#[derive(Clone, Debug)]
+pub struct HeadlessPlatform {
+    pub dpi_scale: f32,
+    /// In physical pixels.
+    pub screen_size: (f32, f32),
+    /// Returned by [`Platform::now`]. Advance it yourself to simulate the passing of time.
+    pub time: f64,
+    pub mouse_shown: bool,
+    pub mouse_cursor: miniquad::CursorIcon,
+    /// Every url egui asked to open.
+    pub opened_urls: Vec<String>,
+    /// How often [`Platform::schedule_update`] was called.
+    pub scheduled_updates: usize,
+    pub fullscreen: bool,
+    pub cursor_grabbed: bool,
+    /// Set by [`Platform::request_quit`], cleared by [`Platform::cancel_quit`].
+    pub quit_requested: bool,
+    /// The paths of the files in the last drop, if known.
+    pub dropped_file_paths: Vec<std::path::PathBuf>,
+    /// The contents of the files in the last drop, if known (only on the web with miniquad).
+    pub dropped_file_bytes: Vec<Vec<u8>>,
+}
+
+impl HeadlessPlatform {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            dpi_scale: 1.0,
+            screen_size: (width, height),
+            time: 0.0,
+            mouse_shown: true,
+            mouse_cursor: miniquad::CursorIcon::Default,
+            opened_urls: Vec::new(),
+            scheduled_updates: 0,
+            fullscreen: false,
+            cursor_grabbed: false,
+            quit_requested: false,
+            dropped_file_paths: Vec::new(),
+            dropped_file_bytes: Vec::new(),
+        }
+    }
+}
+
+impl Platform for HeadlessPlatform {
+    fn dpi_scale(&self) -> f32 {
+        self.dpi_scale
+    }
+
+    fn screen_size(&self) -> (f32, f32) {
+        self.screen_size
+    }
+
+    fn now(&self) -> f64 {
+        self.time
+    }
+
+    fn show_mouse(&mut self, shown: bool) {
+        self.mouse_shown = shown;
+    }
+
+    fn set_mouse_cursor(&mut self, cursor_icon: miniquad::CursorIcon) {
+        self.mouse_cursor = cursor_icon;
+    }
+
+    fn open_url(&mut self, url: &str, _new_tab: bool) {
+        self.opened_urls.push(url.to_owned());
+    }
+
+    fn schedule_update(&mut self) {
+        self.scheduled_updates += 1;
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.fullscreen = fullscreen;
+    }
+
+    /// Resizes [`Self::screen_size`] right away.
+    fn set_window_size(&mut self, width: u32, height: u32) {
+        self.screen_size = (width as f32, height as f32);
+    }
+
+    fn set_cursor_grab(&mut self, grab: bool) {
+        self.cursor_grabbed = grab;
+    }
+
+    fn request_quit(&mut self) {
+        self.quit_requested = true;
+    }
+
+    fn cancel_quit(&mut self) {
+        self.quit_requested = false;
+    }
+
+    fn dropped_file_count(&self) -> usize {
+        self.dropped_file_paths
+            .len()
+            .max(self.dropped_file_bytes.len())
+    }
+
+    fn dropped_file_path(&self, index: usize) -> Option<std::path::PathBuf> {
+        self.dropped_file_paths.get(index).cloned()
+    }
+
+    fn dropped_file_bytes(&self, index: usize) -> Option<Vec<u8>> {
+        self.dropped_file_bytes.get(index).cloned()
+    }
+}
+
+/// A call made to a [`RecordingBackend`].
+///
+/// Buffer contents are not recorded, since miniquad doesn't let other crates read a [`BufferSource`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    NewShader {
+        shader: ShaderId,
+        /// Whether the shader was given in Metal's shading language rather than GLSL.
+        metal: bool,
+    },
+    DeleteShader(ShaderId),
+    NewPipeline(Pipeline),
+    ApplyPipeline(Pipeline),
+    DeletePipeline(Pipeline),
+    NewBuffer {
+        buffer: BufferId,
+        type_: BufferType,
+        usage: BufferUsage,
+        /// `None` for buffers created from a slice, whose element size miniquad keeps private.
+        element_size: Option<usize>,
+    },
+    BufferUpdate(BufferId),
+    DeleteBuffer(BufferId),
+    NewTexture {
+        texture: TextureId,
+        width: u32,
+        height: u32,
+    },
+    TextureUpdatePart {
+        texture: TextureId,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    DeleteTexture(TextureId),
+    NewRenderPass(RenderPass),
+    DeleteRenderPass(RenderPass),
+    BeginPass {
+        pass: Option<RenderPass>,
+        /// `Some` if the pass action clears the color attachment.
+        clear_color: Option<(f32, f32, f32, f32)>,
+    },
+    EndRenderPass,
+    CommitFrame,
+    Clear {
+        color: Option<(f32, f32, f32, f32)>,
+    },
+    ApplyViewport {
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+    },
+    ApplyScissorRect {
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+    },
+    ApplyBindings {
+        vertex_buffers: Vec<BufferId>,
+        index_buffer: BufferId,
+        images: Vec<TextureId>,
+    },
+    ApplyUniforms(Vec<u8>),
+    Draw {
+        base_element: i32,
+        num_elements: i32,
+        num_instances: i32,
+    },
+}
+
+struct Texture {
+    raw_id: u32,
+    params: TextureParams,
+    bytes: Vec<u8>,
+}
+
+/// A [`RenderingBackend`] that records every call as a [`Command`] instead of drawing.
+///
+/// Only meant for tests: the ids it hands out are forged, since miniquad only lets
+/// its own contexts create them.
+///
+/// Texture contents are kept, see [`Self::texture_bytes`].
+pub struct RecordingBackend {
+    backend: Backend,
+    /// What [`RenderingBackend::info`] reports as `gl_version_string`.
+    ///
+    /// Desktop OpenGL (or WebGL 1 on wasm) unless changed, empty for Metal.
+    pub gl_version_string: String,
+    // `draw` only gets `&self`.
+    commands: RefCell<Vec<Command>>,
+    next_id: usize,
+    buffer_sizes: HashMap<BufferId, usize>,
+    textures: HashMap<TextureId, Texture>,
+    render_passes: Vec<(RenderPass, Vec<TextureId>)>,
+}
+
+impl RecordingBackend {
+    /// `backend` is what [`RenderingBackend::info`] reports,
+    /// so the painter can be tested as if running on either backend.
+    pub fn new(backend: Backend) -> Self {
+        let gl_version_string = match backend {
+            Backend::OpenGl if cfg!(target_arch = "wasm32") => "WebGL 1.0",
+            Backend::OpenGl => "3.3.0",
+            Backend::Metal => "",
+        };
+        Self {
+            backend,
+            gl_version_string: gl_version_string.to_owned(),
+            commands: Default::default(),
+            next_id: 1,
+            buffer_sizes: Default::default(),
+            textures: Default::default(),
+            render_passes: Default::default(),
+        }
+    }
+
+    /// The commands recorded so far.
+    pub fn commands(&self) -> std::cell::Ref<'_, Vec<Command>> {
+        self.commands.borrow()
+    }
+
+    /// Return and forget the commands recorded so far, e.g. to look at a single frame.
+    pub fn take_commands(&mut self) -> Vec<Command> {
+        std::mem::take(self.commands.get_mut())
+    }
+
+    /// The current contents of a texture, or `None` if it doesn't exist (anymore).
+    pub fn texture_bytes(&self, texture: TextureId) -> Option<&[u8]> {
+        self.textures
+            .get(&texture)
+            .map(|texture| texture.bytes.as_slice())
+    }
+
+    /// The textures that currently exist.
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    /// What a real context of [`Self::new`]'s backend would return for a texture.
+    ///
+    /// For Metal this is a made-up `MTLTexture` pointer that must not be dereferenced.
+    /// Metal ids only exist on Apple platforms, so elsewhere it is always an OpenGL id.
+    fn raw_id(&self, raw_id: u32) -> RawId {
+        #[cfg(target_vendor = "apple")]
+        if self.backend == Backend::Metal {
+            return RawId::Metal(raw_id as usize as *mut _);
+        }
+        RawId::OpenGl(raw_id)
+    }
+
+    fn record(&self, command: Command) {
+        self.commands.borrow_mut().push(command);
+    }
+
+    fn new_id<T: Copy + std::fmt::Debug>(&mut self) -> T {
+        let id = self.next_id;
+        self.next_id += 1;
+        from_index(id)
+    }
+}
+
+/// Makes a [`BufferId`], [`ShaderId`], [`Pipeline`] or [`RenderPass`].
+///
+/// miniquad has no public way to create these without a real graphics context,
+/// which is why this whole module is meant for tests only.
+/// They are all `struct Id(usize)` in miniquad 0.4. Their layout is not guaranteed
+/// (`repr(Rust)`), but a struct whose only field is a `usize` and which is as large as
+/// a `usize` can't be anything but that `usize`, and every `usize` is valid.
+/// Should miniquad ever change these types, the checks below panic instead.
+fn from_index<T: Copy + std::fmt::Debug>(index: usize) -> T {
+    assert_eq!(
+        std::mem::size_of::<T>(),
+        std::mem::size_of::<usize>(),
+        "miniquad's ids are no longer a single usize"
+    );
+    assert_eq!(std::mem::align_of::<T>(), std::mem::align_of::<usize>());
+    // SAFETY: see above, `T` is exactly one `usize` wide and only holds a `usize`.
+    let id: T = unsafe { std::mem::transmute_copy(&index) };
+    let debug = format!("{id:?}");
+    assert!(
+        debug.ends_with(&format!("({index})")),
+        "miniquad's ids are no longer a single usize: {debug}"
+    );
+    id
+}
+
+impl RenderingBackend for RecordingBackend {
+    fn info(&self) -> ContextInfo {
+        ContextInfo {
+            backend: self.backend,
+            gl_version_string: self.gl_version_string.clone(),
+            glsl_support: Default::default(),
+            features: Default::default(),
+        }
+    }
+
+    fn new_shader(
+        &mut self,
+        shader: ShaderSource,
+        _meta: ShaderMeta,
+    ) -> Result<ShaderId, ShaderError> {
+        let metal = matches!(shader, ShaderSource::Msl { .. });
+        let shader = self.new_id();
+        self.record(Command::NewShader { shader, metal });
+        Ok(shader)
+    }
+
+    fn new_texture(
+        &mut self,
+        _access: TextureAccess,
+        data: TextureSource,
+        params: TextureParams,
+    ) -> TextureId {
+        let raw_id = self.next_id as u32;
+        self.next_id += 1;
+        let texture = TextureId::from_raw_id(self.raw_id(raw_id));
+
+        let size = params.format.size(params.width, params.height) as usize;
+        let bytes = match data {
+            TextureSource::Bytes(bytes) => bytes.to_vec(),
+            TextureSource::Empty | TextureSource::Array(_) => vec![0; size],
+        };
+        self.textures.insert(
+            texture,
+            Texture {
+                raw_id,
+                params,
+                bytes,
+            },
+        );
+        self.record(Command::NewTexture {
+            texture,
+            width: params.width,
+            height: params.height,
+        });
+        texture
+    }
+
+    fn texture_params(&self, texture: TextureId) -> TextureParams {
+        self.textures[&texture].params
+    }
+
+    unsafe fn texture_raw_id(&self, texture: TextureId) -> RawId {
+        self.raw_id(
+            self.textures
+                .get(&texture)
+                .map_or(0, |texture| texture.raw_id),
+        )
+    }
+
+    fn texture_set_min_filter(
+        &mut self,
+        texture: TextureId,
+        filter: FilterMode,
+        mipmap_filter: MipmapFilterMode,
+    ) {
+        if let Some(texture) = self.textures.get_mut(&texture) {
+            texture.params.min_filter = filter;
+            texture.params.mipmap_filter = mipmap_filter;
+        }
+    }
+
+    fn texture_set_mag_filter(&mut self, texture: TextureId, filter: FilterMode) {
+        if let Some(texture) = self.textures.get_mut(&texture) {
+            texture.params.mag_filter = filter;
+        }
+    }
+
+    fn texture_set_wrap(&mut self, texture: TextureId, wrap_x: TextureWrap, _wrap_y: TextureWrap) {
+        if let Some(texture) = self.textures.get_mut(&texture) {
+            texture.params.wrap = wrap_x;
+        }
+    }
+
+    fn texture_generate_mipmaps(&mut self, _texture: TextureId) {}
+
+    fn texture_resize(
+        &mut self,
+        texture: TextureId,
+        width: u32,
+        height: u32,
+        bytes: Option<&[u8]>,
+    ) {
+        if let Some(texture) = self.textures.get_mut(&texture) {
+            texture.params.width = width;
+            texture.params.height = height;
+            let size = texture.params.format.size(width, height) as usize;
+            texture.bytes = bytes.map_or_else(|| vec![0; size], <[u8]>::to_vec);
+        }
+    }
+
+    fn texture_read_pixels(&mut self, texture: TextureId, bytes: &mut [u8]) {
+        if let Some(texture) = self.textures.get(&texture) {
+            let len = bytes.len().min(texture.bytes.len());
+            bytes[..len].copy_from_slice(&texture.bytes[..len]);
+        }
+    }
+
+    fn texture_update_part(
+        &mut self,
+        texture_id: TextureId,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        bytes: &[u8],
+    ) {
+        if let Some(texture) = self.textures.get_mut(&texture_id) {
+            let bytes_per_pixel = texture.params.format.size(1, 1) as usize;
+            let row_len = width as usize * bytes_per_pixel;
+            for row in 0..height as usize {
+                let src = row * row_len;
+                let dst = ((y_offset as usize + row) * texture.params.width as usize
+                    + x_offset as usize)
+                    * bytes_per_pixel;
+                texture.bytes[dst..dst + row_len].copy_from_slice(&bytes[src..src + row_len]);
+            }
+        }
+        self.record(Command::TextureUpdatePart {
+            texture: texture_id,
+            x: x_offset,
+            y: y_offset,
+            width,
+            height,
+        });
+    }
+
+    fn new_render_pass_mrt(
+        &mut self,
+        color_img: &[TextureId],
+        _resolve_img: Option<&[TextureId]>,
+        _depth_img: Option<TextureId>,
+    ) -> RenderPass {
+        let pass = self.new_id();
+        self.render_passes.push((pass, color_img.to_vec()));
+        self.record(Command::NewRenderPass(pass));
+        pass
+    }
+
+    fn render_pass_color_attachments(&self, render_pass: RenderPass) -> &[TextureId] {
+        self.render_passes
+            .iter()
+            .find(|(pass, _)| *pass == render_pass)
+            .map_or(&[], |(_, color_img)| color_img.as_slice())
+    }
+
+    fn delete_render_pass(&mut self, render_pass: RenderPass) {
+        self.render_passes.retain(|(pass, _)| *pass != render_pass);
+        self.record(Command::DeleteRenderPass(render_pass));
+    }
+
+    fn new_pipeline(
+        &mut self,
+        _buffer_layout: &[BufferLayout],
+        _attributes: &[VertexAttribute],
+        _shader: ShaderId,
+        _params: PipelineParams,
+    ) -> Pipeline {
+        let pipeline = self.new_id();
+        self.record(Command::NewPipeline(pipeline));
+        pipeline
+    }
+
+    fn apply_pipeline(&mut self, pipeline: &Pipeline) {
+        self.record(Command::ApplyPipeline(*pipeline));
+    }
+
+    fn delete_pipeline(&mut self, pipeline: Pipeline) {
+        self.record(Command::DeletePipeline(pipeline));
+    }
+
+    fn new_buffer(
+        &mut self,
+        type_: BufferType,
+        usage: BufferUsage,
+        data: BufferSource,
+    ) -> BufferId {
+        let buffer = self.new_id();
+        let (size, element_size) = match data {
+            BufferSource::Empty { size, element_size } => (size, Some(element_size)),
+            // The size of a slice is private to miniquad.
+            BufferSource::Slice(_) => (usize::MAX, None),
+        };
+        self.buffer_sizes.insert(buffer, size);
+        self.record(Command::NewBuffer {
+            buffer,
+            type_,
+            usage,
+            element_size,
+        });
+        buffer
+    }
+
+    fn buffer_update(&mut self, buffer: BufferId, _data: BufferSource) {
+        self.record(Command::BufferUpdate(buffer));
+    }
+
+    fn buffer_size(&mut self, buffer: BufferId) -> usize {
+        self.buffer_sizes.get(&buffer).copied().unwrap_or_default()
+    }
+
+    fn delete_buffer(&mut self, buffer: BufferId) {
+        self.buffer_sizes.remove(&buffer);
+        self.record(Command::DeleteBuffer(buffer));
+    }
+
+    fn delete_texture(&mut self, texture: TextureId) {
+        self.textures.remove(&texture);
+        self.record(Command::DeleteTexture(texture));
+    }
+
+    fn delete_shader(&mut self, program: ShaderId) {
+        self.record(Command::DeleteShader(program));
+    }
+
+    fn apply_viewport(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.record(Command::ApplyViewport { x, y, w, h });
+    }
+
+    fn apply_scissor_rect(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.record(Command::ApplyScissorRect { x, y, w, h });
+    }
+
+    fn apply_bindings_from_slice(
+        &mut self,
+        vertex_buffers: &[BufferId],
+        index_buffer: BufferId,
+        textures: &[TextureId],
+    ) {
+        self.record(Command::ApplyBindings {
+            vertex_buffers: vertex_buffers.to_vec(),
+            index_buffer,
+            images: textures.to_vec(),
+        });
+    }
+
+    #[allow(clippy::not_unsafe_ptr_arg_deref)] // the signature is miniquad's
+    fn apply_uniforms_from_bytes(&mut self, uniform_ptr: *const u8, size: usize) {
+        // SAFETY: miniquad passes a pointer to `size` bytes of uniforms.
+        let bytes = unsafe { std::slice::from_raw_parts(uniform_ptr, size) };
+        self.record(Command::ApplyUniforms(bytes.to_vec()));
+    }
+
+    fn clear(
+        &mut self,
+        color: Option<(f32, f32, f32, f32)>,
+        _depth: Option<f32>,
+        _stencil: Option<i32>,
+    ) {
+        self.record(Command::Clear { color });
+    }
+
+    fn begin_default_pass(&mut self, action: PassAction) {
+        self.begin_pass(None, action);
+    }
+
+    fn begin_pass(&mut self, pass: Option<RenderPass>, action: PassAction) {
+        let clear_color = match action {
+            PassAction::Nothing => None,
+            PassAction::Clear { color, .. } => color,
+        };
+        self.record(Command::BeginPass { pass, clear_color });
+    }
+
+    fn end_render_pass(&mut self) {
+        self.record(Command::EndRenderPass);
+    }
+
+    fn commit_frame(&mut self) {
+        self.record(Command::CommitFrame);
+    }
+
+    fn draw(&self, base_element: i32, num_elements: i32, num_instances: i32) {
+        self.record(Command::Draw {
+            base_element,
+            num_elements,
+            num_instances,
+        });
+    }
+}
+
+/// An [`crate::EguiMq`] drawing to a [`RecordingBackend`], for the tests of this crate.
+#[cfg(test)]
+pub(crate) fn test_egui_mq(
+    backend: Backend,
+) -> (
+    RecordingBackend,
+    std::rc::Rc<RefCell<HeadlessPlatform>>,
+    crate::EguiMq,
+) {
+    let mut backend = RecordingBackend::new(backend);
+    let platform = std::rc::Rc::new(RefCell::new(HeadlessPlatform::new(800.0, 600.0)));
+    let egui_mq = crate::EguiMq::with_platform(&mut backend, platform.clone());
+    (backend, platform, egui_mq)
+}
+
+/// Run and draw a frame, returning the input events egui got.
+#[cfg(test)]
+pub(crate) fn run_frame(
+    egui_mq: &mut crate::EguiMq,
+    backend: &mut RecordingBackend,
+    mut run_ui: impl FnMut(&egui::Context),
+) -> Vec<egui::Event> {
+    let mut events = Vec::new();
+    egui_mq.run(backend, |_mq_ctx, egui_ctx| {
+        events = egui_ctx.input(|i| i.raw.events.clone());
+        run_ui(egui_ctx);
+    });
+    egui_mq.draw(backend);
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forged_ids_are_unique() {
+        let mut backend = RecordingBackend::new(Backend::OpenGl);
+        let a = backend.new_buffer(
+            BufferType::VertexBuffer,
+            BufferUsage::Stream,
+            BufferSource::empty::<u8>(4),
+        );
+        let b = backend.new_buffer(
+            BufferType::VertexBuffer,
+            BufferUsage::Stream,
+            BufferSource::empty::<u8>(4),
+        );
+        assert_ne!(a, b);
+        assert_eq!(format!("{a:?}"), "BufferId(1)");
+    }
+
+    #[test]
+    fn draws_every_mesh_with_its_own_scissor() {
+        let (mut backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        // Windows are invisible in their first frame, while egui finds their size.
+        for _ in 0..2 {
+            backend.take_commands();
+            run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+                egui::Window::new("Window").show(egui_ctx, |ui| {
+                    ui.label("Hello World!");
+                });
+            });
+        }
+
+        let commands = backend.take_commands();
+        let count = |f: fn(&Command) -> bool| commands.iter().filter(|c| f(c)).count();
+        let draws = count(|c| matches!(c, Command::Draw { .. }));
+        assert!(draws > 0);
+        assert_eq!(
+            count(|c| matches!(c, Command::ApplyScissorRect { .. })),
+            draws
+        );
+        assert_eq!(count(|c| matches!(c, Command::ApplyBindings { .. })), draws);
+        assert_eq!(
+            count(|c| matches!(c, Command::BeginPass { pass: None, .. })),
+            1
+        );
+        assert_eq!(commands.last(), Some(&Command::EndRenderPass));
+
+        for command in &commands {
+            if let Command::ApplyScissorRect { x, y, w, h } = *command {
+                assert!(x >= 0 && y >= 0 && x + w <= 800 && y + h <= 600);
+            }
+        }
+    }
+}
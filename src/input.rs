@@ -1,14 +1,19 @@
 use miniquad as mq;
 
-pub fn on_frame_start(egui_input: &mut egui::RawInput, equi_ctx: &egui::Context) {
-    let screen_size_in_pixels = mq::window::screen_size();
-    let screen_size_in_points =
-        egui::vec2(screen_size_in_pixels.0, screen_size_in_pixels.1) / equi_ctx.pixels_per_point();
+use crate::Platform;
+
+pub fn on_frame_start(
+    egui_input: &mut egui::RawInput,
+    equi_ctx: &egui::Context,
+    platform: &dyn Platform,
+    screen_size_in_pixels: egui::Vec2,
+) {
+    let screen_size_in_points = screen_size_in_pixels / equi_ctx.pixels_per_point();
     egui_input.screen_rect = Some(egui::Rect::from_min_size(
         Default::default(),
         screen_size_in_points,
     ));
-    egui_input.time = Some(mq::date::now());
+    egui_input.time = Some(platform.now());
 }
 
 /// miniquad sends special keys (backspace, delete, F1, ...) as characters.
@@ -39,6 +44,162 @@ pub fn egui_modifiers_from_mq_modifiers(keymods: mq::KeyMods) -> egui::Modifiers
     }
 }
 
+/// Command+X, or Shift+Delete outside of macOS.
+pub fn is_cut_command(modifiers: egui::Modifiers, keycode: mq::KeyCode) -> bool {
+    (modifiers.command && keycode == mq::KeyCode::X)
+        || (cfg!(not(target_os = "macos"))
+            && modifiers.shift_only()
+            && keycode == mq::KeyCode::Delete)
+}
+
+/// Command+C, or Ctrl+Insert outside of macOS.
+pub fn is_copy_command(modifiers: egui::Modifiers, keycode: mq::KeyCode) -> bool {
+    (modifiers.command && keycode == mq::KeyCode::C)
+        || (cfg!(not(target_os = "macos"))
+            && modifiers.command_only()
+            && keycode == mq::KeyCode::Insert)
+}
+
+/// Command+V, or Shift+Insert outside of macOS.
+pub fn is_paste_command(modifiers: egui::Modifiers, keycode: mq::KeyCode) -> bool {
+    (modifiers.command && keycode == mq::KeyCode::V)
+        || (cfg!(not(target_os = "macos"))
+            && modifiers.shift_only()
+            && keycode == mq::KeyCode::Insert)
+}
+
+/// What the deltas given to [`crate::EguiMq::mouse_wheel_event`] are measured in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WheelUnit {
+    /// Guess from the platform and the deltas:
+    /// on Windows 120 is one line (one notch of a mouse wheel),
+    /// X11 sends ±1 per line, and everywhere else (macOS, Wayland, the web, ...)
+    /// the deltas are points.
+    ///
+    /// miniquad doesn't say which backend or unit an event comes from, so this can be wrong:
+    /// * On Linux, deltas of exactly 0 or ±1 are taken as X11 lines, so a Wayland or trackpad
+    ///   scroll by a single point moves by a whole line.
+    /// * On the web, browsers that scroll by lines (`DOM_DELTA_LINE`) send a few lines,
+    ///   which miniquad truncates to whole numbers, and these are taken as points.
+    ///
+    /// If you know what your platform sends, use one of the other units instead.
+    Auto,
+    Point,
+    Line,
+    Page,
+}
+
+/// How [`crate::EguiMq::mouse_wheel_event`] turns wheel events into egui events,
+/// see [`crate::EguiMq::set_wheel_options`].
+///
+/// ```
+/// let wheel_options = egui_miniquad::WheelOptions {
+///     scroll_multiplier: 2.0,
+///     ctrl_zooms: true,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WheelOptions {
+    /// Defaults to [`WheelUnit::Auto`].
+    pub unit: WheelUnit,
+    /// All deltas are multiplied by this. Defaults to 1.
+    pub scroll_multiplier: f32,
+    /// Scroll horizontally while Shift is held. Defaults to `true`.
+    ///
+    /// egui does this by itself too, so turning it off also hides Shift from the wheel event.
+    pub shift_scrolls_horizontally: bool,
+    /// Send [`egui::Event::Zoom`] instead of scrolling while Ctrl (Cmd on macOS) is held.
+    ///
+    /// Defaults to `false`, in which case egui zooms by itself, smoothed over a few frames.
+    pub ctrl_zooms: bool,
+}
+
+impl Default for WheelOptions {
+    fn default() -> Self {
+        Self {
+            unit: WheelUnit::Auto,
+            scroll_multiplier: 1.0,
+            shift_scrolls_horizontally: true,
+            ctrl_zooms: false,
+        }
+    }
+}
+
+/// The unit of a miniquad wheel delta, and the delta in that unit.
+pub fn egui_wheel_delta(unit: WheelUnit, delta: egui::Vec2) -> (egui::MouseWheelUnit, egui::Vec2) {
+    match unit {
+        WheelUnit::Auto => {
+            let is_notch = |d: f32| d == 0.0 || d.abs() == 1.0;
+            if cfg!(target_os = "windows") {
+                (egui::MouseWheelUnit::Line, delta / 120.0) // WHEEL_DELTA
+            } else if cfg!(all(
+                unix,
+                not(any(
+                    target_os = "macos",
+                    target_os = "ios",
+                    target_os = "android"
+                ))
+            )) && is_notch(delta.x)
+                && is_notch(delta.y)
+            {
+                // X11. Wayland sends points.
+                (egui::MouseWheelUnit::Line, delta)
+            } else {
+                (egui::MouseWheelUnit::Point, delta)
+            }
+        }
+        WheelUnit::Point => (egui::MouseWheelUnit::Point, delta),
+        WheelUnit::Line => (egui::MouseWheelUnit::Line, delta),
+        WheelUnit::Page => (egui::MouseWheelUnit::Page, delta),
+    }
+}
+
+pub fn egui_touch_phase_from_mq_touch_phase(phase: mq::TouchPhase) -> egui::TouchPhase {
+    match phase {
+        mq::TouchPhase::Started => egui::TouchPhase::Start,
+        mq::TouchPhase::Moved => egui::TouchPhase::Move,
+        mq::TouchPhase::Ended => egui::TouchPhase::End,
+        mq::TouchPhase::Cancelled => egui::TouchPhase::Cancel,
+    }
+}
+
+/// The key at the position of `key` on a US keyboard, for [`egui::Event::Key::physical_key`].
+///
+/// miniquad reports a single key code. On Windows (scan codes), macOS (virtual key codes)
+/// and the web (`KeyboardEvent.code`) it names the position of the key, so it is also the
+/// physical key. On Linux and Android it comes from the keyboard layout, so the physical key
+/// is unknown. egui has no numpad keys, so those have no physical key either.
+pub fn physical_key(key: mq::KeyCode) -> Option<egui::Key> {
+    if !cfg!(any(
+        target_os = "windows",
+        target_os = "macos",
+        target_arch = "wasm32"
+    )) {
+        return None;
+    }
+    match key {
+        mq::KeyCode::Kp0
+        | mq::KeyCode::Kp1
+        | mq::KeyCode::Kp2
+        | mq::KeyCode::Kp3
+        | mq::KeyCode::Kp4
+        | mq::KeyCode::Kp5
+        | mq::KeyCode::Kp6
+        | mq::KeyCode::Kp7
+        | mq::KeyCode::Kp8
+        | mq::KeyCode::Kp9
+        | mq::KeyCode::KpDecimal
+        | mq::KeyCode::KpDivide
+        | mq::KeyCode::KpMultiply
+        | mq::KeyCode::KpSubtract
+        | mq::KeyCode::KpAdd
+        | mq::KeyCode::KpEnter
+        | mq::KeyCode::KpEqual => None,
+        key => egui_key_from_mq_key(key),
+    }
+}
+
 pub fn egui_key_from_mq_key(key: mq::KeyCode) -> Option<egui::Key> {
     Some(match key {
         mq::KeyCode::Down => egui::Key::ArrowDown,
@@ -106,10 +267,287 @@ pub fn egui_key_from_mq_key(key: mq::KeyCode) -> Option<egui::Key> {
         mq::KeyCode::Period => egui::Key::Period,
         mq::KeyCode::Equal => egui::Key::Equals,
         mq::KeyCode::Semicolon => egui::Key::Semicolon,
+        mq::KeyCode::Apostrophe => egui::Key::Quote,
+        mq::KeyCode::Slash => egui::Key::Slash,
 
-        _other => {
-            // mq::trace!("egui-miniquad: Unhandled key: {:?}", _other);
-            return None;
-        }
+        mq::KeyCode::F1 => egui::Key::F1,
+        mq::KeyCode::F2 => egui::Key::F2,
+        mq::KeyCode::F3 => egui::Key::F3,
+        mq::KeyCode::F4 => egui::Key::F4,
+        mq::KeyCode::F5 => egui::Key::F5,
+        mq::KeyCode::F6 => egui::Key::F6,
+        mq::KeyCode::F7 => egui::Key::F7,
+        mq::KeyCode::F8 => egui::Key::F8,
+        mq::KeyCode::F9 => egui::Key::F9,
+        mq::KeyCode::F10 => egui::Key::F10,
+        mq::KeyCode::F11 => egui::Key::F11,
+        mq::KeyCode::F12 => egui::Key::F12,
+        mq::KeyCode::F13 => egui::Key::F13,
+        mq::KeyCode::F14 => egui::Key::F14,
+        mq::KeyCode::F15 => egui::Key::F15,
+        mq::KeyCode::F16 => egui::Key::F16,
+        mq::KeyCode::F17 => egui::Key::F17,
+        mq::KeyCode::F18 => egui::Key::F18,
+        mq::KeyCode::F19 => egui::Key::F19,
+        mq::KeyCode::F20 => egui::Key::F20,
+        mq::KeyCode::F21 => egui::Key::F21,
+        mq::KeyCode::F22 => egui::Key::F22,
+        mq::KeyCode::F23 => egui::Key::F23,
+        mq::KeyCode::F24 => egui::Key::F24,
+        mq::KeyCode::F25 => egui::Key::F25,
+
+        // egui has no separate numpad keys, so map them to what they type.
+        mq::KeyCode::Kp0 => egui::Key::Num0,
+        mq::KeyCode::Kp1 => egui::Key::Num1,
+        mq::KeyCode::Kp2 => egui::Key::Num2,
+        mq::KeyCode::Kp3 => egui::Key::Num3,
+        mq::KeyCode::Kp4 => egui::Key::Num4,
+        mq::KeyCode::Kp5 => egui::Key::Num5,
+        mq::KeyCode::Kp6 => egui::Key::Num6,
+        mq::KeyCode::Kp7 => egui::Key::Num7,
+        mq::KeyCode::Kp8 => egui::Key::Num8,
+        mq::KeyCode::Kp9 => egui::Key::Num9,
+        mq::KeyCode::KpDecimal => egui::Key::Period,
+        mq::KeyCode::KpDivide => egui::Key::Slash,
+        mq::KeyCode::KpSubtract => egui::Key::Minus,
+        mq::KeyCode::KpAdd => egui::Key::Plus,
+        mq::KeyCode::KpEnter => egui::Key::Enter,
+        mq::KeyCode::KpEqual => egui::Key::Equals,
+
+        // No egui equivalent. Listed explicitly (rather than with `_`) so that
+        // the compiler tells us when miniquad adds a key we should map.
+        mq::KeyCode::KpMultiply
+        | mq::KeyCode::World1
+        | mq::KeyCode::World2
+        | mq::KeyCode::CapsLock
+        | mq::KeyCode::ScrollLock
+        | mq::KeyCode::NumLock
+        | mq::KeyCode::PrintScreen
+        | mq::KeyCode::Pause
+        | mq::KeyCode::LeftShift
+        | mq::KeyCode::LeftControl
+        | mq::KeyCode::LeftAlt
+        | mq::KeyCode::LeftSuper
+        | mq::KeyCode::RightShift
+        | mq::KeyCode::RightControl
+        | mq::KeyCode::RightAlt
+        | mq::KeyCode::RightSuper
+        | mq::KeyCode::Menu
+        | mq::KeyCode::Back
+        | mq::KeyCode::Unknown => return None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Key;
+    use mq::KeyCode;
+
+    #[test]
+    fn translates_keys() {
+        let table = [
+            (KeyCode::Down, Key::ArrowDown),
+            (KeyCode::Left, Key::ArrowLeft),
+            (KeyCode::Right, Key::ArrowRight),
+            (KeyCode::Up, Key::ArrowUp),
+            (KeyCode::Escape, Key::Escape),
+            (KeyCode::Tab, Key::Tab),
+            (KeyCode::Backspace, Key::Backspace),
+            (KeyCode::Enter, Key::Enter),
+            (KeyCode::Space, Key::Space),
+            (KeyCode::Insert, Key::Insert),
+            (KeyCode::Delete, Key::Delete),
+            (KeyCode::Home, Key::Home),
+            (KeyCode::End, Key::End),
+            (KeyCode::PageUp, Key::PageUp),
+            (KeyCode::PageDown, Key::PageDown),
+            (KeyCode::Key0, Key::Num0),
+            (KeyCode::Key1, Key::Num1),
+            (KeyCode::Key2, Key::Num2),
+            (KeyCode::Key3, Key::Num3),
+            (KeyCode::Key4, Key::Num4),
+            (KeyCode::Key5, Key::Num5),
+            (KeyCode::Key6, Key::Num6),
+            (KeyCode::Key7, Key::Num7),
+            (KeyCode::Key8, Key::Num8),
+            (KeyCode::Key9, Key::Num9),
+            (KeyCode::A, Key::A),
+            (KeyCode::B, Key::B),
+            (KeyCode::C, Key::C),
+            (KeyCode::D, Key::D),
+            (KeyCode::E, Key::E),
+            (KeyCode::F, Key::F),
+            (KeyCode::G, Key::G),
+            (KeyCode::H, Key::H),
+            (KeyCode::I, Key::I),
+            (KeyCode::J, Key::J),
+            (KeyCode::K, Key::K),
+            (KeyCode::L, Key::L),
+            (KeyCode::M, Key::M),
+            (KeyCode::N, Key::N),
+            (KeyCode::O, Key::O),
+            (KeyCode::P, Key::P),
+            (KeyCode::Q, Key::Q),
+            (KeyCode::R, Key::R),
+            (KeyCode::S, Key::S),
+            (KeyCode::T, Key::T),
+            (KeyCode::U, Key::U),
+            (KeyCode::V, Key::V),
+            (KeyCode::W, Key::W),
+            (KeyCode::X, Key::X),
+            (KeyCode::Y, Key::Y),
+            (KeyCode::Z, Key::Z),
+            (KeyCode::Comma, Key::Comma),
+            (KeyCode::Backslash, Key::Backslash),
+            (KeyCode::LeftBracket, Key::OpenBracket),
+            (KeyCode::RightBracket, Key::CloseBracket),
+            (KeyCode::GraveAccent, Key::Backtick),
+            (KeyCode::Minus, Key::Minus),
+            (KeyCode::Period, Key::Period),
+            (KeyCode::Equal, Key::Equals),
+            (KeyCode::Semicolon, Key::Semicolon),
+            (KeyCode::Apostrophe, Key::Quote),
+            (KeyCode::Slash, Key::Slash),
+            (KeyCode::F1, Key::F1),
+            (KeyCode::F2, Key::F2),
+            (KeyCode::F3, Key::F3),
+            (KeyCode::F4, Key::F4),
+            (KeyCode::F5, Key::F5),
+            (KeyCode::F6, Key::F6),
+            (KeyCode::F7, Key::F7),
+            (KeyCode::F8, Key::F8),
+            (KeyCode::F9, Key::F9),
+            (KeyCode::F10, Key::F10),
+            (KeyCode::F11, Key::F11),
+            (KeyCode::F12, Key::F12),
+            (KeyCode::F13, Key::F13),
+            (KeyCode::F14, Key::F14),
+            (KeyCode::F15, Key::F15),
+            (KeyCode::F16, Key::F16),
+            (KeyCode::F17, Key::F17),
+            (KeyCode::F18, Key::F18),
+            (KeyCode::F19, Key::F19),
+            (KeyCode::F20, Key::F20),
+            (KeyCode::F21, Key::F21),
+            (KeyCode::F22, Key::F22),
+            (KeyCode::F23, Key::F23),
+            (KeyCode::F24, Key::F24),
+            (KeyCode::F25, Key::F25),
+            (KeyCode::Kp0, Key::Num0),
+            (KeyCode::Kp1, Key::Num1),
+            (KeyCode::Kp2, Key::Num2),
+            (KeyCode::Kp3, Key::Num3),
+            (KeyCode::Kp4, Key::Num4),
+            (KeyCode::Kp5, Key::Num5),
+            (KeyCode::Kp6, Key::Num6),
+            (KeyCode::Kp7, Key::Num7),
+            (KeyCode::Kp8, Key::Num8),
+            (KeyCode::Kp9, Key::Num9),
+            (KeyCode::KpDecimal, Key::Period),
+            (KeyCode::KpDivide, Key::Slash),
+            (KeyCode::KpSubtract, Key::Minus),
+            (KeyCode::KpAdd, Key::Plus),
+            (KeyCode::KpEnter, Key::Enter),
+            (KeyCode::KpEqual, Key::Equals),
+        ];
+        for (mq_key, egui_key) in table {
+            assert_eq!(egui_key_from_mq_key(mq_key), Some(egui_key), "{mq_key:?}");
+        }
+
+        // Together with the table above, this is every miniquad key.
+        let unhandled = [
+            KeyCode::KpMultiply,
+            KeyCode::World1,
+            KeyCode::World2,
+            KeyCode::CapsLock,
+            KeyCode::ScrollLock,
+            KeyCode::NumLock,
+            KeyCode::PrintScreen,
+            KeyCode::Pause,
+            KeyCode::LeftShift,
+            KeyCode::LeftControl,
+            KeyCode::LeftAlt,
+            KeyCode::LeftSuper,
+            KeyCode::RightShift,
+            KeyCode::RightControl,
+            KeyCode::RightAlt,
+            KeyCode::RightSuper,
+            KeyCode::Menu,
+            KeyCode::Back,
+            KeyCode::Unknown,
+        ];
+        for mq_key in unhandled {
+            assert_eq!(egui_key_from_mq_key(mq_key), None, "{mq_key:?}");
+        }
+    }
+
+    #[test]
+    fn physical_keys() {
+        let table = [
+            (KeyCode::A, Some(Key::A)),
+            (KeyCode::Z, Some(Key::Z)),
+            (KeyCode::Key5, Some(Key::Num5)),
+            (KeyCode::Slash, Some(Key::Slash)),
+            (KeyCode::Enter, Some(Key::Enter)),
+            (KeyCode::Kp0, None),
+            (KeyCode::Kp5, None),
+            (KeyCode::Kp9, None),
+            (KeyCode::KpDecimal, None),
+            (KeyCode::KpDivide, None),
+            (KeyCode::KpMultiply, None),
+            (KeyCode::KpSubtract, None),
+            (KeyCode::KpAdd, None),
+            (KeyCode::KpEnter, None),
+            (KeyCode::KpEqual, None),
+            (KeyCode::CapsLock, None),
+        ];
+        let positional = cfg!(any(
+            target_os = "windows",
+            target_os = "macos",
+            target_arch = "wasm32"
+        ));
+        for (mq_key, egui_key) in table {
+            let expected = if positional { egui_key } else { None };
+            assert_eq!(physical_key(mq_key), expected, "{mq_key:?}");
+        }
+    }
+
+    #[test]
+    fn wheel_units() {
+        let delta = egui::vec2(2.0, -3.0);
+        for (unit, egui_unit) in [
+            (WheelUnit::Point, egui::MouseWheelUnit::Point),
+            (WheelUnit::Line, egui::MouseWheelUnit::Line),
+            (WheelUnit::Page, egui::MouseWheelUnit::Page),
+        ] {
+            assert_eq!(egui_wheel_delta(unit, delta), (egui_unit, delta));
+        }
+    }
+
+    #[test]
+    fn wheel_unit_is_detected() {
+        let auto = |x, y| egui_wheel_delta(WheelUnit::Auto, egui::vec2(x, y));
+        if cfg!(target_os = "windows") {
+            assert_eq!(
+                auto(0.0, -240.0),
+                (egui::MouseWheelUnit::Line, egui::vec2(0.0, -2.0))
+            );
+        } else if cfg!(target_os = "linux") {
+            // X11 sends notches, Wayland points:
+            assert_eq!(
+                auto(0.0, -1.0),
+                (egui::MouseWheelUnit::Line, egui::vec2(0.0, -1.0))
+            );
+            assert_eq!(
+                auto(0.0, -2.5),
+                (egui::MouseWheelUnit::Point, egui::vec2(0.0, -2.5))
+            );
+        } else {
+            assert_eq!(
+                auto(0.0, -1.0),
+                (egui::MouseWheelUnit::Point, egui::vec2(0.0, -1.0))
+            );
+        }
+    }
+}
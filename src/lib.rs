@@ -1,7 +1,9 @@
 //! [egui](https://github.com/emilk/egui) bindings for [miniquad](https://github.com/not-fl3/miniquad).
 //!
 //! ## Usage
-//! Create an instance of [`EguiMq`] and call its event-handler from
+//! The quickest way is to implement [`EguiApp`] and run it in an [`EguiStage`].
+//!
+//! Or create an instance of [`EguiMq`] and call its event-handler from
 //! your `miniquad::EventHandler` implementation.
 //!
 //! In your `miniquad::EventHandler::draw` method do this:
@@ -85,9 +87,9 @@
 //!         &mut self,
 //!         keycode: mq::KeyCode,
 //!         keymods: mq::KeyMods,
-//!         _repeat: bool,
+//!         repeat: bool,
 //!     ) {
-//!         self.egui_mq.key_down_event(keycode, keymods);
+//!         self.egui_mq.key_down_event(keycode, keymods, repeat);
 //!     }
 //!
 //!     fn key_up_event(&mut self, keycode: mq::KeyCode, keymods: mq::KeyMods) {
@@ -96,8 +98,18 @@
 //! }
 //! ```
 
+mod builder;
+mod clipboard;
+#[cfg(any(test, feature = "headless"))]
+pub mod headless;
 mod input;
 mod painter;
+#[cfg(feature = "persistence")]
+pub mod persistence;
+mod platform;
+#[cfg(feature = "rasterizer")]
+pub mod rasterizer;
+mod stage;
 
 // ----------------------------------------------------------------------------
 
@@ -118,10 +130,12 @@ getrandom::register_custom_getrandom!(getrandom);
 use egui::CursorIcon;
 use miniquad as mq;
 
-pub use painter::CallbackFn;
-
-#[cfg(target_os = "macos")] // https://github.com/not-fl3/miniquad/issues/172
-use copypasta::ClipboardProvider;
+pub use builder::EguiMqBuilder;
+pub use clipboard::{ClipboardError, ClipboardProvider, InMemoryClipboard, SystemClipboard};
+pub use input::{WheelOptions, WheelUnit};
+pub use painter::{CallbackFn, PainterOptions, SharedPainter};
+pub use platform::{MiniquadPlatform, Platform};
+pub use stage::{EguiApp, EguiStage};
 
 /// egui bindings for miniquad.
 ///
@@ -134,27 +148,235 @@ pub struct EguiMq {
     egui_ctx: egui::Context,
     egui_input: egui::RawInput,
     painter: painter::Painter,
-    #[cfg(target_os = "macos")]
-    clipboard: Option<copypasta::ClipboardContext>,
+    platform: Box<dyn Platform>,
+    clipboard: Box<dyn ClipboardProvider>,
+    /// Called when egui's output couldn't be put in the clipboard.
+    on_clipboard_error: Box<dyn FnMut(ClipboardError)>,
     shapes: Option<Vec<egui::epaint::ClippedShape>>,
     textures_delta: egui::TexturesDelta,
+    /// Also send pointer events for the first finger on the screen.
+    touch_emulates_pointer: bool,
+    /// The touch that is currently emulating the pointer.
+    primary_touch: Option<u64>,
+    /// Where the text cursor was last frame, if text is being edited.
+    ime: Option<egui::output::IMEOutput>,
+    wheel_options: WheelOptions,
+    /// Called with key presses that have no [`egui::Key`] equivalent.
+    on_unhandled_key: Option<Box<dyn FnMut(mq::KeyCode)>>,
+    /// The part of the window egui lives in, in physical pixels. `None` is the whole window.
+    viewport: Option<egui::Rect>,
+    /// Set by [`Self::set_target_size`].
+    target_size: Option<(u32, u32)>,
+    /// Whether the mouse was in [`Self::viewport`] when it last moved.
+    pointer_in_viewport: bool,
+    /// Mouse buttons pressed in [`Self::viewport`] and not yet released.
+    /// One bit per [`egui::PointerButton`].
+    held_buttons: u8,
+    /// Touches that started in [`Self::viewport`] and haven't ended yet.
+    touches_in_viewport: Vec<u64>,
+    /// How long after [`Self::last_run_time`] egui wants to run again.
+    repaint_delay: std::time::Duration,
+    /// [`Platform::now`] at the last [`Self::run`].
+    last_run_time: f64,
+    /// Set by [`egui::ViewportCommand::CursorVisible`].
+    cursor_visible: bool,
+    /// [`egui::Context::wants_pointer_input`] at the end of the last [`Self::run`].
+    wants_pointer_input: bool,
+    /// [`egui::Context::wants_keyboard_input`] at the end of the last [`Self::run`].
+    wants_keyboard_input: bool,
+    /// [`egui::Context::is_pointer_over_area`] at the end of the last [`Self::run`].
+    pointer_over_area: bool,
+    /// egui was told the window wants to close, and hasn't had a frame to cancel it yet.
+    close_requested: bool,
+    /// egui didn't cancel the last close, so the next [`Self::quit_requested_event`] may quit.
+    close_allowed: bool,
+    /// The root [`egui::ViewportInfo`] changed since the last [`Self::run`].
+    viewport_info_changed: bool,
+    /// Viewport commands we have already said we don't support, so we only say it once.
+    unsupported_commands: Vec<std::mem::Discriminant<egui::ViewportCommand>>,
+    #[cfg(feature = "persistence")]
+    storage: Option<Box<dyn persistence::Storage>>,
+    #[cfg(feature = "persistence")]
+    auto_save_interval: std::time::Duration,
+    /// [`Platform::now`] when [`egui::Memory`] was last saved.
+    #[cfg(feature = "persistence")]
+    last_save_time: f64,
 }
 
 impl EguiMq {
+    /// See [`EguiMqBuilder`] for more options.
     pub fn new(mq_ctx: &mut dyn mq::RenderingBackend) -> Self {
-        let native_dpi_scale = miniquad::window::dpi_scale();
+        EguiMqBuilder::new().build(mq_ctx)
+    }
+
+    /// Like [`Self::new`], but gets the window size, DPI, cursor etc. from `platform`
+    /// instead of the miniquad window.
+    pub fn with_platform(
+        mq_ctx: &mut dyn mq::RenderingBackend,
+        platform: impl Platform + 'static,
+    ) -> Self {
+        EguiMqBuilder::new().platform(platform).build(mq_ctx)
+    }
+
+    /// Like [`Self::new`], but restores [`egui::Memory`] (window positions, collapsed headers
+    /// etc.) from `storage`, and saves it there every 30 seconds and when dropped.
+    ///
+    /// ```
+    /// # fn f(mq_ctx: &mut dyn miniquad::RenderingBackend) {
+    /// # #[cfg(not(target_arch = "wasm32"))]
+    /// let storage = egui_miniquad::persistence::FileStorage::new("egui.ron");
+    /// # #[cfg(target_arch = "wasm32")]
+    /// # let storage = egui_miniquad::persistence::LocalStorage::default();
+    /// let egui_mq = egui_miniquad::EguiMq::new_with_storage(mq_ctx, storage);
+    /// # }
+    /// ```
+    #[cfg(feature = "persistence")]
+    pub fn new_with_storage(
+        mq_ctx: &mut dyn mq::RenderingBackend,
+        storage: impl persistence::Storage + 'static,
+    ) -> Self {
+        EguiMqBuilder::new().storage(storage).build(mq_ctx)
+    }
+
+    /// Restore [`egui::Memory`] from `storage`, and save it there from now on.
+    /// See [`Self::new_with_storage`].
+    #[cfg(feature = "persistence")]
+    pub fn with_storage(mut self, storage: impl persistence::Storage + 'static) -> Self {
+        if let Some(memory) = persistence::load_memory(&storage) {
+            self.egui_ctx.memory_mut(|m| *m = memory);
+        }
+        self.storage = Some(Box::new(storage));
+        self
+    }
+
+    /// How often [`Self::run`] saves [`egui::Memory`] to the storage given to
+    /// [`Self::new_with_storage`]. 30 seconds by default.
+    #[cfg(feature = "persistence")]
+    pub fn set_auto_save_interval(&mut self, auto_save_interval: std::time::Duration) {
+        self.auto_save_interval = auto_save_interval;
+    }
 
-        Self {
-            native_dpi_scale,
-            pixels_per_point: native_dpi_scale,
-            egui_ctx: egui::Context::default(),
-            painter: painter::Painter::new(mq_ctx),
-            egui_input: egui::RawInput::default(),
-            #[cfg(target_os = "macos")]
-            clipboard: init_clipboard(),
-            shapes: None,
-            textures_delta: Default::default(),
+    /// Save [`egui::Memory`] to the storage given to [`Self::new_with_storage`] now.
+    ///
+    /// This also happens when `EguiMq` is dropped, but call this from
+    /// [`miniquad::EventHandler::quit_requested_event`] if you are not sure it will be.
+    #[cfg(feature = "persistence")]
+    pub fn save(&mut self) {
+        if let Some(storage) = &mut self.storage {
+            persistence::save_memory(&mut **storage, &self.egui_ctx);
         }
+        self.last_save_time = self.platform.now();
+    }
+
+    /// Use `clipboard` for copy, cut and paste instead of the [`SystemClipboard`].
+    ///
+    /// ```
+    /// # fn f(mq_ctx: &mut dyn miniquad::RenderingBackend) {
+    /// let egui_mq = egui_miniquad::EguiMq::new(mq_ctx)
+    ///     .with_clipboard(egui_miniquad::InMemoryClipboard::default());
+    /// # }
+    /// ```
+    pub fn with_clipboard(mut self, clipboard: impl ClipboardProvider + 'static) -> Self {
+        self.clipboard = Box::new(clipboard);
+        self
+    }
+
+    /// Called from [`Self::run`] when an image egui wants to copy couldn't be put in
+    /// the clipboard, e.g. because the [`ClipboardProvider`] doesn't support images.
+    /// The default [`SystemClipboard`] doesn't.
+    ///
+    /// By default the error is printed to stderr.
+    pub fn set_clipboard_error_callback(&mut self, callback: impl FnMut(ClipboardError) + 'static) {
+        self.on_clipboard_error = Box::new(callback);
+    }
+
+    /// If `true`, the first finger touching the screen also moves and clicks the egui pointer,
+    /// on top of the [`egui::Event::Touch`] events sent by [`Self::touch_event`].
+    ///
+    /// Off by default, since miniquad's default [`miniquad::EventHandler::touch_event`]
+    /// already emulates mouse events.
+    pub fn set_touch_emulates_pointer(&mut self, touch_emulates_pointer: bool) {
+        self.touch_emulates_pointer = touch_emulates_pointer;
+    }
+
+    /// How mouse wheel and trackpad scrolling is sent to egui.
+    pub fn set_wheel_options(&mut self, wheel_options: WheelOptions) {
+        self.wheel_options = wheel_options;
+    }
+
+    pub fn wheel_options(&self) -> WheelOptions {
+        self.wheel_options
+    }
+
+    /// Called from [`Self::key_down_event`] with keys that have no [`egui::Key`] equivalent
+    /// (e.g. [`miniquad::KeyCode::CapsLock`]), which egui never sees.
+    ///
+    /// Useful for finding out why a shortcut doesn't work:
+    /// ```
+    /// # fn f(egui_mq: &mut egui_miniquad::EguiMq) {
+    /// egui_mq.set_unhandled_key_callback(|key| eprintln!("Unhandled key: {key:?}"));
+    /// # }
+    /// ```
+    pub fn set_unhandled_key_callback(&mut self, callback: impl FnMut(mq::KeyCode) + 'static) {
+        self.on_unhandled_key = Some(Box::new(callback));
+    }
+
+    /// Only use this part of the window, in physical pixels from the top left corner,
+    /// e.g. for split-screen or an editor with an embedded game view.
+    ///
+    /// egui sees the viewport as the whole screen: it is drawn there, and only gets
+    /// mouse and touch input that starts there. `None` (the default) is the whole window.
+    pub fn set_viewport(&mut self, viewport: Option<egui::Rect>) {
+        self.viewport = viewport;
+    }
+
+    /// See [`Self::set_viewport`].
+    pub fn viewport(&self) -> Option<egui::Rect> {
+        self.viewport
+    }
+
+    /// The size egui is laid out for by [`Self::run`], in physical pixels, when drawing it
+    /// with [`Self::draw_to_pass`] into something other than the window.
+    ///
+    /// `None` (the default) is the size of the [viewport](Self::set_viewport) or window.
+    pub fn set_target_size(&mut self, target_size_in_pixels: Option<(u32, u32)>) {
+        self.target_size = target_size_in_pixels;
+    }
+
+    /// See [`Self::set_target_size`].
+    pub fn target_size(&self) -> Option<(u32, u32)> {
+        self.target_size
+    }
+
+    /// What egui sees as the screen: the target size, viewport or window, in physical pixels.
+    fn screen_size_in_pixels(&self) -> egui::Vec2 {
+        match (self.target_size, self.viewport) {
+            (Some((width, height)), _) => egui::vec2(width as f32, height as f32),
+            (None, Some(viewport)) => viewport.size(),
+            (None, None) => {
+                let (width, height) = self.platform.screen_size();
+                egui::vec2(width, height)
+            }
+        }
+    }
+
+    /// Convert a position in the window (in physical pixels) to egui points.
+    fn pos_in_points(&self, x: f32, y: f32) -> egui::Pos2 {
+        let offset = self
+            .viewport
+            .map_or(egui::Vec2::ZERO, |viewport| viewport.min.to_vec2());
+        (egui::pos2(x, y) - offset) / self.egui_ctx.pixels_per_point()
+    }
+
+    /// Queue an input event for the next [`Self::run`], and make sure there is one.
+    fn push_event(&mut self, event: egui::Event) {
+        self.egui_input.events.push(event);
+        self.platform.schedule_update();
+    }
+
+    fn is_in_viewport(&self, x: f32, y: f32) -> bool {
+        self.viewport
+            .map_or(true, |viewport| viewport.contains(egui::pos2(x, y)))
     }
 
     /// Use this to open egui windows, panels etc.
@@ -170,11 +392,17 @@ impl EguiMq {
         mq_ctx: &mut dyn mq::RenderingBackend,
         mut run_ui: impl FnMut(&mut dyn mq::RenderingBackend, &egui::Context),
     ) {
-        input::on_frame_start(&mut self.egui_input, &self.egui_ctx);
+        let screen_size_in_pixels = self.screen_size_in_pixels();
+        input::on_frame_start(
+            &mut self.egui_input,
+            &self.egui_ctx,
+            &*self.platform,
+            screen_size_in_pixels,
+        );
 
-        if self.native_dpi_scale != miniquad::window::dpi_scale() {
+        if self.native_dpi_scale != self.platform.dpi_scale() {
             // DPI scale change (maybe new monitor?). Tell egui to change:
-            self.native_dpi_scale = miniquad::window::dpi_scale();
+            self.native_dpi_scale = self.platform.dpi_scale();
             self.egui_input
                 .viewports
                 .get_mut(&self.egui_input.viewport_id)
@@ -186,14 +414,43 @@ impl EguiMq {
             .egui_ctx
             .run(self.egui_input.take(), |egui_ctx| run_ui(mq_ctx, egui_ctx));
 
+        self.wants_pointer_input = self.egui_ctx.wants_pointer_input();
+        self.wants_keyboard_input = self.egui_ctx.wants_keyboard_input();
+        self.pointer_over_area = self.egui_ctx.is_pointer_over_area();
+
         let egui::FullOutput {
             platform_output,
             textures_delta,
             shapes,
             pixels_per_point,
-            viewport_output: _viewport_output, // we only support one viewport
+            viewport_output,
         } = full_output;
 
+        // We only support one viewport:
+        let root_output = viewport_output.get(&egui::ViewportId::ROOT);
+        self.repaint_delay =
+            root_output.map_or(std::time::Duration::MAX, |output| output.repaint_delay);
+        if let Some(output) = root_output {
+            for command in &output.commands {
+                self.apply_viewport_command(command, pixels_per_point);
+            }
+        }
+        self.viewport_info_changed = false;
+        if std::mem::take(&mut self.close_requested)
+            && !root_output.map_or(false, |output| {
+                output
+                    .commands
+                    .contains(&egui::ViewportCommand::CancelClose)
+            })
+        {
+            self.close_allowed = true;
+            self.platform.request_quit();
+        }
+        self.last_run_time = self.platform.now();
+        if self.repaint_delay.is_zero() {
+            self.platform.schedule_update();
+        }
+
         if self.shapes.is_some() {
             eprintln!("Egui contents not drawn. You need to call `draw` after calling `run`");
         }
@@ -204,41 +461,198 @@ impl EguiMq {
         let egui::PlatformOutput {
             commands,
             cursor_icon,
-            events: _,                    // no screen reader
-            ime: _,                       // no IME
-            mutable_text_under_cursor: _, // no IME
+            events: _, // no screen reader
+            ime,
+            mutable_text_under_cursor: _, // the host's IME can't use it
             ..
         } = platform_output;
 
+        self.ime = ime;
+
         for command in commands {
             match command {
                 egui::OutputCommand::OpenUrl(open_url) => {
-                    quad_url::link_open(&open_url.url, open_url.new_tab);
+                    self.platform.open_url(&open_url.url, open_url.new_tab);
                 }
                 egui::OutputCommand::CopyText(copied_text) => {
-                    self.set_clipboard(copied_text);
+                    self.clipboard.set(&copied_text);
+                }
+                egui::OutputCommand::CopyImage(image) => {
+                    if let Err(err) = self.clipboard.set_image(&image) {
+                        (self.on_clipboard_error)(err);
+                    }
                 }
-                egui::OutputCommand::CopyImage(_) => (), // No implementation for miniquad
             }
         }
 
-        if cursor_icon == egui::CursorIcon::None {
-            miniquad::window::show_mouse(false);
+        if cursor_icon == egui::CursorIcon::None || !self.cursor_visible {
+            self.platform.show_mouse(false);
         } else {
-            miniquad::window::show_mouse(true);
+            self.platform.show_mouse(true);
             let mq_cursor_icon = to_mq_cursor_icon(cursor_icon);
             let mq_cursor_icon = mq_cursor_icon.unwrap_or(mq::CursorIcon::Default);
-            miniquad::window::set_mouse_cursor(mq_cursor_icon);
+            self.platform.set_mouse_cursor(mq_cursor_icon);
+        }
+
+        #[cfg(feature = "persistence")]
+        if self.storage.is_some()
+            && self.platform.now() - self.last_save_time >= self.auto_save_interval.as_secs_f64()
+        {
+            self.save();
+        }
+    }
+
+    fn apply_viewport_command(&mut self, command: &egui::ViewportCommand, pixels_per_point: f32) {
+        match command {
+            egui::ViewportCommand::Close => self.platform.request_quit(),
+            egui::ViewportCommand::CancelClose => {} // see `Self::quit_requested_event`
+            egui::ViewportCommand::Fullscreen(fullscreen) => {
+                self.platform.set_fullscreen(*fullscreen);
+            }
+            egui::ViewportCommand::InnerSize(size) => {
+                let size = *size * pixels_per_point;
+                self.platform
+                    .set_window_size(size.x.round() as u32, size.y.round() as u32);
+            }
+            egui::ViewportCommand::CursorVisible(visible) => self.cursor_visible = *visible,
+            egui::ViewportCommand::CursorGrab(grab) => {
+                self.platform
+                    .set_cursor_grab(*grab != egui::viewport::CursorGrab::None);
+            }
+            // Like the keyboard shortcuts, these arrive as input next frame:
+            egui::ViewportCommand::RequestCut => self.push_event(egui::Event::Cut),
+            egui::ViewportCommand::RequestCopy => self.push_event(egui::Event::Copy),
+            egui::ViewportCommand::RequestPaste => {
+                if let Some(text) = self.clipboard.get() {
+                    self.push_event(egui::Event::Paste(text));
+                }
+            }
+            command => {
+                // E.g. `Title`, which miniquad can only set at startup.
+                let discriminant = std::mem::discriminant(command);
+                if !self.unsupported_commands.contains(&discriminant) {
+                    self.unsupported_commands.push(discriminant);
+                    eprintln!("egui-miniquad: Unsupported viewport command: {command:?}");
+                }
+            }
         }
     }
 
+    /// Whether egui is using the mouse or touch input, e.g. because it is over an egui window
+    /// or dragging a slider. If so, your app should ignore mouse and touch events.
+    ///
+    /// This is as of the end of the last [`Self::run`], so that every event between two
+    /// frames gets the same answer.
+    ///
+    /// ```
+    /// # use miniquad as mq;
+    /// # struct Game { egui_mq: egui_miniquad::EguiMq }
+    /// # impl Game { fn fire_weapon(&mut self) {} }
+    /// impl Game {
+    ///     fn mouse_button_down_event(&mut self, mb: mq::MouseButton, x: f32, y: f32) {
+    ///         self.egui_mq.mouse_button_down_event(mb, x, y);
+    ///         if !self.egui_mq.wants_pointer_input() {
+    ///             self.fire_weapon();
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn wants_pointer_input(&self) -> bool {
+        self.wants_pointer_input
+    }
+
+    /// Whether egui is using the keyboard, e.g. because a text field has focus.
+    /// If so, your app should ignore key and char events.
+    ///
+    /// This is as of the end of the last [`Self::run`].
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.wants_keyboard_input
+    }
+
+    /// Whether the mouse is over an egui window or panel, as of the end of the last [`Self::run`].
+    ///
+    /// Unlike [`Self::wants_pointer_input`], this is false while dragging something in egui
+    /// out of its window.
+    pub fn is_pointer_over_area(&self) -> bool {
+        self.pointer_over_area
+    }
+
+    /// Whether egui has new input (including a resize or a quit request),
+    /// or asked to run again by now.
+    ///
+    /// If this is false, you can skip [`Self::run`] and [`Self::draw`] (and your whole frame)
+    /// if nothing else in your app changed.
+    pub fn needs_repaint(&self) -> bool {
+        !self.egui_input.events.is_empty()
+            || !self.egui_input.dropped_files.is_empty()
+            || self.close_requested
+            || self.viewport_info_changed
+            || self.repaint_after().is_zero()
+    }
+
+    /// How long until egui wants to run again, if it gets no new input.
+    /// [`std::time::Duration::MAX`] if it doesn't.
+    ///
+    /// With `conf.platform.blocking_event_loop`, [`EguiMq`] calls
+    /// [`miniquad::window::schedule_update`] on input and when egui wants to run again
+    /// right away. miniquad can't wake up after a delay though, so if this returns something
+    /// in between, you need to schedule that update yourself (or the next event will do it).
+    pub fn repaint_after(&self) -> std::time::Duration {
+        if self.repaint_delay == std::time::Duration::MAX {
+            return self.repaint_delay;
+        }
+        let elapsed = (self.platform.now() - self.last_run_time).max(0.0);
+        self.repaint_delay
+            .saturating_sub(std::time::Duration::from_secs_f64(elapsed))
+    }
+
     /// Call this when you need to draw egui.
     /// Must be called after `end_frame`.
     pub fn draw(&mut self, mq_ctx: &mut dyn mq::RenderingBackend) {
+        let (width, height) = self.platform.screen_size();
+        self.draw_to_pass(
+            mq_ctx,
+            None,
+            mq::PassAction::Nothing,
+            (width.round() as u32, height.round() as u32),
+        );
+    }
+
+    /// Like [`Self::draw`], but draws into `pass` instead of the window
+    /// (`None` is the default framebuffer).
+    ///
+    /// `target_size_in_pixels` is the size of the pass' color attachment,
+    /// e.g. `mq_ctx.texture_size(mq_ctx.render_pass_texture(pass))`.
+    /// Unless that is the size of the window, tell [`Self::run`] about it first with
+    /// [`Self::set_target_size`], or egui is laid out for the wrong size
+    /// (this is checked, and printed to stderr).
+    /// The pass is begun with `action`, so it can be cleared before egui is drawn.
+    pub fn draw_to_pass(
+        &mut self,
+        mq_ctx: &mut dyn mq::RenderingBackend,
+        pass: Option<mq::RenderPass>,
+        action: mq::PassAction,
+        target_size_in_pixels: (u32, u32),
+    ) {
+        if self.viewport.is_none() {
+            let size = self.screen_size_in_pixels().round();
+            let laid_out_size = (size.x as u32, size.y as u32);
+            if laid_out_size != target_size_in_pixels {
+                eprintln!(
+                    "egui was laid out for {:?} pixels but is drawn to {:?}. See `EguiMq::set_target_size`",
+                    laid_out_size, target_size_in_pixels
+                );
+            }
+        }
         if let Some(shapes) = self.shapes.take() {
             let meshes = self.egui_ctx.tessellate(shapes, self.pixels_per_point);
             self.painter.paint_and_update_textures(
                 mq_ctx,
+                pass,
+                action,
+                target_size_in_pixels,
+                self.platform.screen_size().1,
+                self.viewport,
                 meshes,
                 &self.textures_delta,
                 &self.egui_ctx,
@@ -249,35 +663,107 @@ impl EguiMq {
         }
     }
 
+    /// Like [`Self::draw`], but draws on the CPU with `rasterizer` and returns the image,
+    /// which is as large as the [target size](Self::set_target_size),
+    /// [viewport](Self::set_viewport) or [`Platform::screen_size`].
+    ///
+    /// Use this instead of [`Self::draw`] for frames you want to look at in tests.
+    /// Create `rasterizer` with the same [`PainterOptions`] as this [`EguiMq`].
+    #[cfg(feature = "rasterizer")]
+    pub fn draw_to_image(
+        &mut self,
+        rasterizer: &mut rasterizer::SoftwareRasterizer,
+    ) -> egui::ColorImage {
+        let size = self.screen_size_in_pixels().round();
+        let size_in_pixels = [size.x as usize, size.y as usize];
+        if let Some(shapes) = self.shapes.take() {
+            let meshes = self.egui_ctx.tessellate(shapes, self.pixels_per_point);
+            let image = rasterizer.paint_and_update_textures(
+                size_in_pixels,
+                &meshes,
+                &self.textures_delta,
+                self.egui_ctx.pixels_per_point(),
+            );
+            self.textures_delta.clear();
+            image
+        } else {
+            eprintln!("Failed to draw egui. You need to call `end_frame` before calling `draw`");
+            egui::ColorImage::new(size_in_pixels, egui::Color32::TRANSPARENT)
+        }
+    }
+
     /// Call from your [`miniquad::EventHandler`].
     pub fn mouse_motion_event(&mut self, x: f32, y: f32) {
-        let pos = egui::pos2(
-            x / self.egui_ctx.pixels_per_point(),
-            y / self.egui_ctx.pixels_per_point(),
-        );
-        self.egui_input.events.push(egui::Event::PointerMoved(pos))
+        let in_viewport = self.is_in_viewport(x, y);
+        if in_viewport || self.held_buttons > 0 {
+            let pos = self.pos_in_points(x, y);
+            self.push_event(egui::Event::PointerMoved(pos))
+        } else if self.pointer_in_viewport {
+            self.push_event(egui::Event::PointerGone);
+        }
+        self.pointer_in_viewport = in_viewport;
+    }
+
+    /// Tell egui the mouse has left the window, so nothing stays hovered.
+    ///
+    /// miniquad doesn't report this, so call it if you find out some other way.
+    /// [`Self::window_minimized_event`] calls it too, since that is also sent on focus loss.
+    pub fn mouse_left(&mut self) {
+        self.pointer_in_viewport = false;
+        self.push_event(egui::Event::PointerGone);
     }
 
     /// Call from your [`miniquad::EventHandler`].
+    ///
+    /// See [`Self::set_wheel_options`].
     pub fn mouse_wheel_event(&mut self, dx: f32, dy: f32) {
-        let delta = egui::vec2(dx, dy);
-        let modifiers = self.egui_input.modifiers;
+        if !self.pointer_in_viewport {
+            return;
+        }
+
+        let options = self.wheel_options;
+        let (unit, delta) = input::egui_wheel_delta(options.unit, egui::vec2(dx, dy));
+        let mut delta = delta * options.scroll_multiplier;
+        let mut modifiers = self.egui_input.modifiers;
+
+        if options.ctrl_zooms && (modifiers.ctrl || modifiers.command) {
+            let points = match unit {
+                egui::MouseWheelUnit::Point => delta.y,
+                egui::MouseWheelUnit::Line => {
+                    self.egui_ctx.options(|o| o.line_scroll_speed) * delta.y
+                }
+                egui::MouseWheelUnit::Page => self.egui_ctx.screen_rect().height() * delta.y,
+            };
+            let zoom_speed = self.egui_ctx.options(|o| o.scroll_zoom_speed);
+            self.push_event(egui::Event::Zoom((zoom_speed * points).exp()));
+            return;
+        }
 
-        self.egui_input.events.push(egui::Event::MouseWheel {
+        if modifiers.shift {
+            if options.shift_scrolls_horizontally {
+                delta = egui::vec2(delta.x + delta.y, 0.0);
+            } else {
+                modifiers.shift = false;
+            }
+        }
+
+        self.push_event(egui::Event::MouseWheel {
             modifiers,
-            unit: egui::MouseWheelUnit::Line,
+            unit,
             delta,
         });
     }
 
     /// Call from your [`miniquad::EventHandler`].
     pub fn mouse_button_down_event(&mut self, mb: mq::MouseButton, x: f32, y: f32) {
-        let pos = egui::pos2(
-            x / self.egui_ctx.pixels_per_point(),
-            y / self.egui_ctx.pixels_per_point(),
-        );
+        if !self.is_in_viewport(x, y) {
+            return;
+        }
         let button = to_egui_button(mb);
-        self.egui_input.events.push(egui::Event::PointerButton {
+        self.held_buttons |= 1 << button as u8;
+
+        let pos = self.pos_in_points(x, y);
+        self.push_event(egui::Event::PointerButton {
             pos,
             button,
             pressed: true,
@@ -287,13 +773,17 @@ impl EguiMq {
 
     /// Call from your [`miniquad::EventHandler`].
     pub fn mouse_button_up_event(&mut self, mb: mq::MouseButton, x: f32, y: f32) {
-        let pos = egui::pos2(
-            x / self.egui_ctx.pixels_per_point(),
-            y / self.egui_ctx.pixels_per_point(),
-        );
+        // Finish drags that started in the viewport, wherever they end,
+        // and ignore the rest, whose presses egui never saw:
         let button = to_egui_button(mb);
+        if self.held_buttons & (1 << button as u8) == 0 {
+            return;
+        }
+        self.held_buttons &= !(1 << button as u8);
 
-        self.egui_input.events.push(egui::Event::PointerButton {
+        let pos = self.pos_in_points(x, y);
+
+        self.push_event(egui::Event::PointerButton {
             pos,
             button,
             pressed: false,
@@ -301,39 +791,236 @@ impl EguiMq {
         })
     }
 
+    /// Call from your [`miniquad::EventHandler`].
+    pub fn touch_event(&mut self, phase: mq::TouchPhase, id: u64, x: f32, y: f32) {
+        match phase {
+            mq::TouchPhase::Started if self.is_in_viewport(x, y) => {
+                self.touches_in_viewport.push(id);
+            }
+            mq::TouchPhase::Started => return,
+            mq::TouchPhase::Moved if !self.touches_in_viewport.contains(&id) => return,
+            mq::TouchPhase::Moved => {}
+            mq::TouchPhase::Ended | mq::TouchPhase::Cancelled => {
+                if !self.touches_in_viewport.contains(&id) {
+                    return;
+                }
+                self.touches_in_viewport.retain(|&touch| touch != id);
+            }
+        }
+
+        let pos = self.pos_in_points(x, y);
+        self.push_event(egui::Event::Touch {
+            device_id: egui::TouchDeviceId(0), // miniquad doesn't tell us
+            id: egui::TouchId(id),
+            phase: input::egui_touch_phase_from_mq_touch_phase(phase),
+            pos,
+            force: None,
+        });
+
+        if !self.touch_emulates_pointer {
+            return;
+        }
+
+        match phase {
+            mq::TouchPhase::Started if self.primary_touch.is_none() => {
+                self.primary_touch = Some(id);
+                self.push_event(egui::Event::PointerMoved(pos));
+                self.push_event(egui::Event::PointerButton {
+                    pos,
+                    button: egui::PointerButton::Primary,
+                    pressed: true,
+                    modifiers: self.egui_input.modifiers,
+                });
+            }
+            mq::TouchPhase::Moved if self.primary_touch == Some(id) => {
+                self.push_event(egui::Event::PointerMoved(pos));
+            }
+            mq::TouchPhase::Ended if self.primary_touch == Some(id) => {
+                self.primary_touch = None;
+                self.push_event(egui::Event::PointerButton {
+                    pos,
+                    button: egui::PointerButton::Primary,
+                    pressed: false,
+                    modifiers: self.egui_input.modifiers,
+                });
+                // There is no hovering with touch:
+                self.push_event(egui::Event::PointerGone);
+            }
+            mq::TouchPhase::Cancelled if self.primary_touch == Some(id) => {
+                self.primary_touch = None;
+                self.push_event(egui::Event::PointerGone);
+            }
+            _ => {}
+        }
+    }
+
+    /// Where the IME candidate window should be placed, in physical pixels.
+    ///
+    /// This is the text cursor of the focused [`egui::TextEdit`] as of the last [`Self::run`],
+    /// or `None` if no text is being edited.
+    pub fn ime_cursor_rect(&self) -> Option<egui::Rect> {
+        let offset = self
+            .viewport
+            .map_or(egui::Vec2::ZERO, |viewport| viewport.min.to_vec2());
+        self.ime
+            .map(|ime| (ime.cursor_rect * self.pixels_per_point).translate(offset))
+    }
+
+    /// Call when the host's IME starts a composition.
+    pub fn ime_preedit_start(&mut self) {
+        self.push_event(egui::Event::Ime(egui::ImeEvent::Enabled));
+    }
+
+    /// Call when the text being composed by the host's IME changes.
+    pub fn ime_preedit_update(&mut self, text: &str) {
+        self.push_event(egui::Event::Ime(egui::ImeEvent::Preedit(text.to_owned())));
+    }
+
+    /// Call when the host's IME commits the composed text.
+    pub fn ime_commit(&mut self, text: &str) {
+        self.push_event(egui::Event::Ime(egui::ImeEvent::Commit(text.to_owned())));
+    }
+
+    /// Call when the host's IME ends a composition, with or without committing it.
+    pub fn ime_preedit_end(&mut self) {
+        self.push_event(egui::Event::Ime(egui::ImeEvent::Disabled));
+    }
+
+    /// Call from your [`miniquad::EventHandler`].
+    ///
+    /// The dropped files show up in [`egui::InputState::raw`] next frame.
+    /// miniquad doesn't report files hovering the window, so `hovered_files` stays empty.
+    pub fn files_dropped_event(&mut self) {
+        for index in 0..self.platform.dropped_file_count() {
+            let path = self.platform.dropped_file_path(index);
+            let name = path
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let bytes = self.platform.dropped_file_bytes(index);
+
+            self.egui_input.dropped_files.push(egui::DroppedFile {
+                path,
+                name,
+                bytes: bytes.map(Into::into),
+                ..Default::default()
+            });
+        }
+        self.platform.schedule_update();
+    }
+
+    /// Call from your [`miniquad::EventHandler`].
+    ///
+    /// Only on Windows and Android does miniquad send this for a hidden window. On X11, Wayland
+    /// and the web it means the window lost focus, so there egui is only told the window is
+    /// unfocused, not minimized. Either way the mouse is gone.
+    pub fn window_minimized_event(&mut self) {
+        self.set_focused(false);
+        if cfg!(any(target_os = "windows", target_os = "android")) {
+            self.root_viewport_info().minimized = Some(true);
+        }
+        self.mouse_left();
+    }
+
+    /// Call from your [`miniquad::EventHandler`].
+    ///
+    /// Without a [`Self::set_viewport`], the mouse is assumed to be back in the window,
+    /// so the wheel works again before it moves.
+    pub fn window_restored_event(&mut self) {
+        self.set_focused(true);
+        self.root_viewport_info().minimized = Some(false);
+        if self.viewport.is_none() {
+            self.pointer_in_viewport = true;
+        }
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.egui_input.focused = focused;
+        self.root_viewport_info().focused = Some(focused);
+        self.push_event(egui::Event::WindowFocused(focused));
+    }
+
+    /// Call from your [`miniquad::EventHandler`], with the new size in physical pixels.
+    ///
+    /// This sets the `inner_rect` of the [`egui::ViewportInfo`].
+    pub fn resize_event(&mut self, width: f32, height: f32) {
+        let size = egui::vec2(width, height) / self.egui_ctx.pixels_per_point();
+        self.root_viewport_info().inner_rect =
+            Some(egui::Rect::from_min_size(egui::Pos2::ZERO, size));
+        self.platform.schedule_update();
+    }
+
+    /// Call from your [`miniquad::EventHandler`].
+    ///
+    /// The quit is put on hold for one frame, in which
+    /// [`egui::ViewportInfo::close_requested`] is true. Unless the app then sends
+    /// [`egui::ViewportCommand::CancelClose`], e.g. to ask about unsaved changes,
+    /// the quit is requested again and goes through.
+    ///
+    /// ```
+    /// # fn ui(egui_ctx: &egui::Context, unsaved_changes: bool) {
+    /// if egui_ctx.input(|i| i.viewport().close_requested()) && unsaved_changes {
+    ///     egui_ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+    ///     // Show a "Save changes?" dialog
+    /// }
+    /// # }
+    /// ```
+    pub fn quit_requested_event(&mut self) {
+        if std::mem::take(&mut self.close_allowed) {
+            return;
+        }
+        self.platform.cancel_quit();
+        self.close_requested = true;
+        self.root_viewport_info()
+            .events
+            .push(egui::ViewportEvent::Close);
+        self.platform.schedule_update();
+    }
+
+    fn root_viewport_info(&mut self) -> &mut egui::ViewportInfo {
+        self.viewport_info_changed = true;
+        self.egui_input
+            .viewports
+            .entry(egui::ViewportId::ROOT)
+            .or_default()
+    }
+
     /// Call from your [`miniquad::EventHandler`].
     pub fn char_event(&mut self, chr: char) {
         if input::is_printable_char(chr)
             && !self.egui_input.modifiers.ctrl
             && !self.egui_input.modifiers.mac_cmd
         {
-            self.egui_input
-                .events
-                .push(egui::Event::Text(chr.to_string()));
+            self.push_event(egui::Event::Text(chr.to_string()));
         }
     }
 
     /// Call from your [`miniquad::EventHandler`].
-    pub fn key_down_event(&mut self, keycode: mq::KeyCode, keymods: mq::KeyMods) {
+    ///
+    /// `repeat` is true for events generated by holding the key down.
+    pub fn key_down_event(&mut self, keycode: mq::KeyCode, keymods: mq::KeyMods, repeat: bool) {
         let modifiers = input::egui_modifiers_from_mq_modifiers(keymods);
         self.egui_input.modifiers = modifiers;
 
-        if modifiers.command && keycode == mq::KeyCode::X {
-            self.egui_input.events.push(egui::Event::Cut);
-        } else if modifiers.command && keycode == mq::KeyCode::C {
-            self.egui_input.events.push(egui::Event::Copy);
-        } else if modifiers.command && keycode == mq::KeyCode::V {
-            if let Some(text) = self.get_clipboard() {
-                self.egui_input.events.push(egui::Event::Text(text));
+        if input::is_cut_command(modifiers, keycode) {
+            self.push_event(egui::Event::Cut);
+        } else if input::is_copy_command(modifiers, keycode) {
+            self.push_event(egui::Event::Copy);
+        } else if input::is_paste_command(modifiers, keycode) {
+            if let Some(text) = self.clipboard.get() {
+                self.push_event(egui::Event::Paste(text));
             }
         } else if let Some(key) = input::egui_key_from_mq_key(keycode) {
-            self.egui_input.events.push(egui::Event::Key {
+            self.push_event(egui::Event::Key {
                 key,
                 pressed: true,
                 modifiers,
-                repeat: false,      // egui will set this for us
-                physical_key: None, // unsupported
+                repeat,
+                physical_key: input::physical_key(keycode),
             })
+        } else if let Some(on_unhandled_key) = &mut self.on_unhandled_key {
+            on_unhandled_key(keycode);
         }
     }
 
@@ -342,59 +1029,21 @@ impl EguiMq {
         let modifiers = input::egui_modifiers_from_mq_modifiers(keymods);
         self.egui_input.modifiers = modifiers;
         if let Some(key) = input::egui_key_from_mq_key(keycode) {
-            self.egui_input.events.push(egui::Event::Key {
+            self.push_event(egui::Event::Key {
                 key,
                 pressed: false,
                 modifiers,
-                repeat: false,      // egui will set this for us
-                physical_key: None, // unsupported
+                repeat: false,
+                physical_key: input::physical_key(keycode),
             })
         }
     }
-
-    #[cfg(not(target_os = "macos"))]
-    fn set_clipboard(&mut self, text: String) {
-        mq::window::clipboard_set(&text);
-    }
-
-    #[cfg(not(target_os = "macos"))]
-    fn get_clipboard(&mut self) -> Option<String> {
-        mq::window::clipboard_get()
-    }
-
-    #[cfg(target_os = "macos")]
-    fn set_clipboard(&mut self, text: String) {
-        if let Some(clipboard) = &mut self.clipboard {
-            if let Err(err) = clipboard.set_contents(text) {
-                eprintln!("Copy/Cut error: {}", err);
-            }
-        }
-    }
-
-    #[cfg(target_os = "macos")]
-    fn get_clipboard(&mut self) -> Option<String> {
-        if let Some(clipboard) = &mut self.clipboard {
-            match clipboard.get_contents() {
-                Ok(contents) => Some(contents),
-                Err(err) => {
-                    eprintln!("Paste error: {}", err);
-                    None
-                }
-            }
-        } else {
-            None
-        }
-    }
 }
 
-#[cfg(target_os = "macos")]
-fn init_clipboard() -> Option<copypasta::ClipboardContext> {
-    match copypasta::ClipboardContext::new() {
-        Ok(clipboard) => Some(clipboard),
-        Err(err) => {
-            eprintln!("Failed to initialize clipboard: {}", err);
-            None
-        }
+#[cfg(feature = "persistence")]
+impl Drop for EguiMq {
+    fn drop(&mut self) {
+        self.save();
     }
 }
 
@@ -453,3 +1102,896 @@ fn to_mq_cursor_icon(cursor_icon: egui::CursorIcon) -> Option<mq::CursorIcon> {
         | egui::CursorIcon::ZoomOut => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::headless::{run_frame, test_egui_mq, Command};
+    use miniquad::{Backend, RenderingBackend as _};
+
+    /// Run frames until egui stops asking for more.
+    fn settle(egui_mq: &mut crate::EguiMq, backend: &mut crate::headless::RecordingBackend) {
+        for _ in 0..10 {
+            run_frame(egui_mq, backend, |_| {});
+            if !egui_mq.needs_repaint() {
+                return;
+            }
+        }
+        panic!("egui keeps repainting");
+    }
+
+    #[test]
+    fn close_can_be_cancelled() {
+        let (mut backend, platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        settle(&mut egui_mq, &mut backend);
+
+        egui_mq.quit_requested_event();
+        assert!(!platform.borrow().quit_requested);
+        assert!(egui_mq.needs_repaint());
+        let mut close_requested = false;
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            close_requested = egui_ctx.input(|i| i.viewport().close_requested());
+            egui_ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        });
+        assert!(close_requested);
+        assert!(!platform.borrow().quit_requested);
+
+        settle(&mut egui_mq, &mut backend);
+        egui_mq.quit_requested_event();
+        assert!(egui_mq.needs_repaint());
+        run_frame(&mut egui_mq, &mut backend, |_| {});
+        assert!(platform.borrow().quit_requested);
+
+        // miniquad sends it again for the quit we requested, which must go through.
+        egui_mq.quit_requested_event();
+        assert!(platform.borrow().quit_requested);
+    }
+
+    #[test]
+    fn wheel_works_after_restore() {
+        let (mut backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        egui_mq.window_minimized_event();
+        assert!(egui_mq
+            .egui_input
+            .events
+            .contains(&egui::Event::PointerGone));
+        egui_mq.window_restored_event();
+        egui_mq.mouse_wheel_event(0.0, 1.0);
+        let events = run_frame(&mut egui_mq, &mut backend, |_| {});
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, egui::Event::MouseWheel { .. })));
+    }
+
+    #[test]
+    fn losing_focus_is_not_minimizing() {
+        let (mut backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        let mut info = egui::ViewportInfo::default();
+        egui_mq.window_minimized_event();
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            info = egui_ctx.input(|i| i.viewport().clone());
+        });
+        assert_eq!(info.focused, Some(false));
+        let iconified = cfg!(any(target_os = "windows", target_os = "android"));
+        assert_eq!(info.minimized == Some(true), iconified);
+
+        egui_mq.window_restored_event();
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            info = egui_ctx.input(|i| i.viewport().clone());
+        });
+        assert_eq!(info.focused, Some(true));
+        assert_eq!(info.minimized, Some(false));
+    }
+
+    #[test]
+    fn mouse_left_sends_pointer_gone() {
+        let (_backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        egui_mq.mouse_motion_event(10.0, 10.0);
+        egui_mq.mouse_left();
+        assert_eq!(
+            egui_mq.egui_input.events.last(),
+            Some(&egui::Event::PointerGone)
+        );
+    }
+
+    #[test]
+    fn draws_to_a_pass_smaller_than_the_window() {
+        let (mut backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        let texture = backend.new_render_texture(miniquad::TextureParams {
+            width: 256,
+            height: 128,
+            ..Default::default()
+        });
+        let pass = backend.new_render_pass(texture, None);
+        egui_mq.set_target_size(Some((256, 128)));
+
+        let mut screen_rect = egui::Rect::NOTHING;
+        egui_mq.run(&mut backend, |_mq_ctx, egui_ctx| {
+            screen_rect = egui_ctx.screen_rect();
+            egui::CentralPanel::default().show(egui_ctx, |ui| ui.label("Hello World!"));
+        });
+        assert_eq!(screen_rect.size(), egui::vec2(256.0, 128.0));
+
+        backend.take_commands();
+        egui_mq.draw_to_pass(
+            &mut backend,
+            Some(pass),
+            miniquad::PassAction::Nothing,
+            (256, 128),
+        );
+        let commands = backend.take_commands();
+        assert!(commands.contains(&Command::BeginPass {
+            pass: Some(pass),
+            clear_color: None
+        }));
+        let screen_size: Vec<u8> = [256.0f32, 128.0]
+            .iter()
+            .flat_map(|x| x.to_ne_bytes())
+            .collect();
+        assert!(commands.contains(&Command::ApplyUniforms(screen_size)));
+        assert!(commands.contains(&Command::ApplyScissorRect {
+            x: 0,
+            y: 0,
+            w: 256,
+            h: 128
+        }));
+    }
+
+    #[test]
+    fn unhandled_keys_go_to_the_callback() {
+        let (mut backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        let unhandled = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let keys = unhandled.clone();
+        egui_mq.set_unhandled_key_callback(move |key| keys.borrow_mut().push(key));
+
+        let keymods = miniquad::KeyMods::default();
+        egui_mq.key_down_event(miniquad::KeyCode::CapsLock, keymods, false);
+        egui_mq.key_down_event(miniquad::KeyCode::Kp5, keymods, false);
+        let events = run_frame(&mut egui_mq, &mut backend, |_| {});
+
+        assert_eq!(*unhandled.borrow(), [miniquad::KeyCode::CapsLock]);
+        assert!(matches!(
+            events.as_slice(),
+            [egui::Event::Key {
+                key: egui::Key::Num5,
+                pressed: true,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn resize_needs_repaint() {
+        let (mut backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        settle(&mut egui_mq, &mut backend);
+
+        egui_mq.resize_event(400.0, 300.0);
+        assert!(egui_mq.needs_repaint());
+        let mut inner_rect = None;
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            inner_rect = egui_ctx.input(|i| i.viewport().inner_rect);
+        });
+        assert_eq!(
+            inner_rect,
+            Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(400.0, 300.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn input_getters_follow_egui() {
+        let (mut backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        let focus_text = std::cell::Cell::new(false);
+        let mut text = String::new();
+        let mut ui = |egui_ctx: &egui::Context| {
+            egui::Window::new("Window")
+                .fixed_pos(egui::pos2(100.0, 100.0))
+                .show(egui_ctx, |ui| {
+                    let response = ui.text_edit_singleline(&mut text);
+                    if focus_text.get() {
+                        response.request_focus();
+                    }
+                });
+        };
+
+        egui_mq.mouse_motion_event(700.0, 500.0);
+        run_frame(&mut egui_mq, &mut backend, &mut ui);
+        run_frame(&mut egui_mq, &mut backend, &mut ui);
+        assert!(!egui_mq.wants_pointer_input());
+        assert!(!egui_mq.is_pointer_over_area());
+        assert!(!egui_mq.wants_keyboard_input());
+
+        egui_mq.mouse_motion_event(110.0, 110.0);
+        run_frame(&mut egui_mq, &mut backend, &mut ui);
+        assert!(egui_mq.wants_pointer_input());
+        assert!(egui_mq.is_pointer_over_area());
+
+        focus_text.set(true);
+        run_frame(&mut egui_mq, &mut backend, &mut ui);
+        assert!(egui_mq.wants_keyboard_input());
+
+        egui_mq.mouse_motion_event(700.0, 500.0);
+        run_frame(&mut egui_mq, &mut backend, &mut ui);
+        assert!(!egui_mq.wants_pointer_input());
+        assert!(!egui_mq.is_pointer_over_area());
+    }
+
+    fn touch(id: u64, phase: egui::TouchPhase, x: f32, y: f32) -> egui::Event {
+        egui::Event::Touch {
+            device_id: egui::TouchDeviceId(0),
+            id: egui::TouchId(id),
+            phase,
+            pos: egui::pos2(x, y),
+            force: None,
+        }
+    }
+
+    fn primary_button(x: f32, y: f32, pressed: bool) -> egui::Event {
+        egui::Event::PointerButton {
+            pos: egui::pos2(x, y),
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn only_the_first_touch_emulates_the_pointer() {
+        use egui::{Event::PointerMoved, TouchPhase::*};
+        use miniquad::TouchPhase;
+
+        let (_backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        egui_mq.set_touch_emulates_pointer(true);
+        egui_mq.touch_event(TouchPhase::Started, 1, 10.0, 10.0);
+        egui_mq.touch_event(TouchPhase::Started, 2, 20.0, 20.0);
+        egui_mq.touch_event(TouchPhase::Moved, 2, 25.0, 25.0);
+        egui_mq.touch_event(TouchPhase::Ended, 2, 25.0, 25.0);
+        egui_mq.touch_event(TouchPhase::Moved, 1, 15.0, 15.0);
+        egui_mq.touch_event(TouchPhase::Ended, 1, 15.0, 15.0);
+
+        assert_eq!(
+            egui_mq.egui_input.events,
+            [
+                touch(1, Start, 10.0, 10.0),
+                PointerMoved(egui::pos2(10.0, 10.0)),
+                primary_button(10.0, 10.0, true),
+                touch(2, Start, 20.0, 20.0),
+                touch(2, Move, 25.0, 25.0),
+                touch(2, End, 25.0, 25.0),
+                touch(1, Move, 15.0, 15.0),
+                PointerMoved(egui::pos2(15.0, 15.0)),
+                touch(1, End, 15.0, 15.0),
+                primary_button(15.0, 15.0, false),
+                egui::Event::PointerGone,
+            ]
+        );
+    }
+
+    #[test]
+    fn cancelling_a_touch_only_releases_its_own_pointer() {
+        use egui::{Event::PointerMoved, TouchPhase::*};
+        use miniquad::TouchPhase;
+
+        let (_backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        egui_mq.set_touch_emulates_pointer(true);
+        egui_mq.touch_event(TouchPhase::Started, 1, 10.0, 10.0);
+        egui_mq.touch_event(TouchPhase::Started, 2, 20.0, 20.0);
+        egui_mq.egui_input.events.clear();
+
+        egui_mq.touch_event(TouchPhase::Cancelled, 2, 20.0, 20.0);
+        egui_mq.touch_event(TouchPhase::Moved, 1, 15.0, 15.0);
+        egui_mq.touch_event(TouchPhase::Cancelled, 1, 15.0, 15.0);
+        // The next touch takes over the pointer:
+        egui_mq.touch_event(TouchPhase::Started, 3, 30.0, 30.0);
+
+        assert_eq!(
+            egui_mq.egui_input.events,
+            [
+                touch(2, Cancel, 20.0, 20.0),
+                touch(1, Move, 15.0, 15.0),
+                PointerMoved(egui::pos2(15.0, 15.0)),
+                touch(1, Cancel, 15.0, 15.0),
+                egui::Event::PointerGone,
+                touch(3, Start, 30.0, 30.0),
+                PointerMoved(egui::pos2(30.0, 30.0)),
+                primary_button(30.0, 30.0, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn touches_without_pointer_emulation() {
+        use miniquad::TouchPhase;
+
+        let (_backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        egui_mq.touch_event(TouchPhase::Started, 1, 10.0, 10.0);
+        egui_mq.touch_event(TouchPhase::Started, 2, 20.0, 20.0);
+        egui_mq.touch_event(TouchPhase::Ended, 1, 10.0, 10.0);
+
+        assert_eq!(
+            egui_mq.egui_input.events,
+            [
+                touch(1, egui::TouchPhase::Start, 10.0, 10.0),
+                touch(2, egui::TouchPhase::Start, 20.0, 20.0),
+                touch(1, egui::TouchPhase::End, 10.0, 10.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn ime_composition() {
+        let (mut backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        let mut text = String::new();
+        let id = egui::Id::new("text");
+        let mut text_edit = |egui_ctx: &egui::Context| {
+            egui::CentralPanel::default().show(egui_ctx, |ui| {
+                ui.add(egui::TextEdit::singleline(&mut text).id(id));
+                ui.memory_mut(|memory| memory.request_focus(id));
+            });
+        };
+        run_frame(&mut egui_mq, &mut backend, &mut text_edit);
+
+        egui_mq.ime_preedit_start();
+        egui_mq.ime_preedit_update("にほ");
+        egui_mq.ime_preedit_update("日本");
+        egui_mq.ime_commit("日本");
+        egui_mq.ime_preedit_end();
+        let events = run_frame(&mut egui_mq, &mut backend, &mut text_edit);
+
+        assert_eq!(
+            events,
+            [
+                egui::Event::Ime(egui::ImeEvent::Enabled),
+                egui::Event::Ime(egui::ImeEvent::Preedit("にほ".to_owned())),
+                egui::Event::Ime(egui::ImeEvent::Preedit("日本".to_owned())),
+                egui::Event::Ime(egui::ImeEvent::Commit("日本".to_owned())),
+                egui::Event::Ime(egui::ImeEvent::Disabled),
+            ]
+        );
+        assert_eq!(text, "日本");
+    }
+
+    #[test]
+    fn ime_cursor_rect_is_in_window_pixels() {
+        let (mut backend, platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        platform.borrow_mut().dpi_scale = 2.0;
+        let offset = egui::vec2(100.0, 50.0);
+        egui_mq.set_viewport(Some(egui::Rect::from_min_size(
+            offset.to_pos2(),
+            egui::vec2(400.0, 300.0),
+        )));
+        assert_eq!(egui_mq.ime_cursor_rect(), None);
+
+        let mut text = String::new();
+        let mut text_edit_rect = egui::Rect::NOTHING;
+        for _ in 0..2 {
+            run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+                egui::CentralPanel::default().show(egui_ctx, |ui| {
+                    let response = ui.text_edit_singleline(&mut text);
+                    response.request_focus();
+                    text_edit_rect = response.rect;
+                });
+            });
+        }
+
+        let cursor_rect = egui_mq.ime.unwrap().cursor_rect;
+        assert!(text_edit_rect.intersects(cursor_rect));
+        assert_eq!(
+            egui_mq.ime_cursor_rect(),
+            Some((cursor_rect * 2.0).translate(offset))
+        );
+    }
+
+    /// An [`crate::EguiMq`] with an in-memory clipboard holding `text`.
+    fn egui_mq_with_clipboard(
+        text: Option<&str>,
+    ) -> (
+        crate::headless::RecordingBackend,
+        std::rc::Rc<std::cell::RefCell<crate::InMemoryClipboard>>,
+        crate::EguiMq,
+    ) {
+        let mut backend = crate::headless::RecordingBackend::new(Backend::OpenGl);
+        let platform = crate::headless::HeadlessPlatform::new(800.0, 600.0);
+        let clipboard = std::rc::Rc::new(std::cell::RefCell::new(crate::InMemoryClipboard {
+            text: text.map(str::to_owned),
+            image: None,
+        }));
+        let egui_mq = crate::EguiMqBuilder::new()
+            .platform(platform)
+            .clipboard(clipboard.clone())
+            .build(&mut backend);
+        (backend, clipboard, egui_mq)
+    }
+
+    /// The events for pressing `keycode` with `keymods`.
+    fn key_events(
+        egui_mq: &mut crate::EguiMq,
+        keycode: miniquad::KeyCode,
+        keymods: miniquad::KeyMods,
+    ) -> Vec<egui::Event> {
+        egui_mq.key_down_event(keycode, keymods, false);
+        std::mem::take(&mut egui_mq.egui_input.events)
+    }
+
+    #[test]
+    fn command_shortcuts_cut_copy_and_paste() {
+        use miniquad::KeyCode;
+
+        // Command is Ctrl, except on macOS:
+        let command = miniquad::KeyMods {
+            ctrl: !cfg!(target_os = "macos"),
+            logo: cfg!(target_os = "macos"),
+            ..Default::default()
+        };
+        let (_backend, _clipboard, mut egui_mq) = egui_mq_with_clipboard(Some("pasted"));
+        assert_eq!(
+            key_events(&mut egui_mq, KeyCode::X, command),
+            [egui::Event::Cut]
+        );
+        assert_eq!(
+            key_events(&mut egui_mq, KeyCode::C, command),
+            [egui::Event::Copy]
+        );
+        assert_eq!(
+            key_events(&mut egui_mq, KeyCode::V, command),
+            [egui::Event::Paste("pasted".to_owned())]
+        );
+
+        let (_backend, _clipboard, mut egui_mq) = egui_mq_with_clipboard(None);
+        assert_eq!(key_events(&mut egui_mq, KeyCode::V, command), []);
+    }
+
+    #[test]
+    fn insert_and_delete_shortcuts_outside_of_macos() {
+        use miniquad::{KeyCode, KeyMods};
+
+        let shift = KeyMods {
+            shift: true,
+            ..Default::default()
+        };
+        let ctrl = KeyMods {
+            ctrl: true,
+            ..Default::default()
+        };
+        let (_backend, _clipboard, mut egui_mq) = egui_mq_with_clipboard(Some("pasted"));
+        let shortcuts = [
+            (KeyCode::Delete, shift, egui::Event::Cut),
+            (KeyCode::Insert, ctrl, egui::Event::Copy),
+            (
+                KeyCode::Insert,
+                shift,
+                egui::Event::Paste("pasted".to_owned()),
+            ),
+        ];
+        for (keycode, keymods, event) in shortcuts {
+            let events = key_events(&mut egui_mq, keycode, keymods);
+            if cfg!(target_os = "macos") {
+                assert!(
+                    matches!(events.as_slice(), [egui::Event::Key { .. }]),
+                    "{keycode:?}"
+                );
+            } else {
+                assert_eq!(events, [event], "{keycode:?}");
+            }
+        }
+
+        // Other modifiers are just keys:
+        let ctrl_shift = KeyMods {
+            ctrl: true,
+            shift: true,
+            ..Default::default()
+        };
+        for keycode in [KeyCode::Delete, KeyCode::Insert] {
+            let events = key_events(&mut egui_mq, keycode, ctrl_shift);
+            assert!(
+                matches!(events.as_slice(), [egui::Event::Key { .. }]),
+                "{keycode:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn copied_text_goes_to_the_clipboard() {
+        let (mut backend, clipboard, mut egui_mq) = egui_mq_with_clipboard(None);
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            egui_ctx.copy_text("copied".to_owned());
+        });
+        assert_eq!(clipboard.borrow().text.as_deref(), Some("copied"));
+    }
+
+    /// The events for a wheel event with `modifiers` held.
+    fn wheel_events(
+        egui_mq: &mut crate::EguiMq,
+        modifiers: egui::Modifiers,
+        dx: f32,
+        dy: f32,
+    ) -> Vec<egui::Event> {
+        egui_mq.egui_input.modifiers = modifiers;
+        egui_mq.mouse_wheel_event(dx, dy);
+        std::mem::take(&mut egui_mq.egui_input.events)
+    }
+
+    fn wheel(modifiers: egui::Modifiers, x: f32, y: f32) -> egui::Event {
+        egui::Event::MouseWheel {
+            unit: egui::MouseWheelUnit::Point,
+            delta: egui::vec2(x, y),
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn scroll_multiplier_scales_the_wheel() {
+        let (_backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        egui_mq.set_wheel_options(crate::WheelOptions {
+            unit: crate::WheelUnit::Point,
+            scroll_multiplier: 2.5,
+            ..Default::default()
+        });
+        assert_eq!(
+            wheel_events(&mut egui_mq, egui::Modifiers::NONE, 1.0, -2.0),
+            [wheel(egui::Modifiers::NONE, 2.5, -5.0)]
+        );
+    }
+
+    #[test]
+    fn shift_scrolls_horizontally() {
+        let (_backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        let options = crate::WheelOptions {
+            unit: crate::WheelUnit::Point,
+            ..Default::default()
+        };
+        egui_mq.set_wheel_options(options);
+        assert_eq!(
+            wheel_events(&mut egui_mq, egui::Modifiers::SHIFT, 0.0, -3.0),
+            [wheel(egui::Modifiers::SHIFT, -3.0, 0.0)]
+        );
+
+        egui_mq.set_wheel_options(crate::WheelOptions {
+            shift_scrolls_horizontally: false,
+            ..options
+        });
+        // egui would make it horizontal if it knew about shift:
+        assert_eq!(
+            wheel_events(&mut egui_mq, egui::Modifiers::SHIFT, 0.0, -3.0),
+            [wheel(egui::Modifiers::NONE, 0.0, -3.0)]
+        );
+    }
+
+    #[test]
+    fn ctrl_zooms() {
+        let (_backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        let options = crate::WheelOptions {
+            unit: crate::WheelUnit::Point,
+            ..Default::default()
+        };
+        egui_mq.set_wheel_options(options);
+        assert_eq!(
+            wheel_events(&mut egui_mq, egui::Modifiers::COMMAND, 0.0, 10.0),
+            [wheel(egui::Modifiers::COMMAND, 0.0, 10.0)]
+        );
+
+        egui_mq.set_wheel_options(crate::WheelOptions {
+            ctrl_zooms: true,
+            ..options
+        });
+        let zoom_speed = egui_mq.egui_ctx().options(|o| o.scroll_zoom_speed);
+        assert_eq!(
+            wheel_events(&mut egui_mq, egui::Modifiers::COMMAND, 0.0, 10.0),
+            [egui::Event::Zoom((zoom_speed * 10.0).exp())]
+        );
+        assert_eq!(
+            wheel_events(&mut egui_mq, egui::Modifiers::CTRL, 0.0, -10.0),
+            [egui::Event::Zoom((zoom_speed * -10.0).exp())]
+        );
+
+        // Lines are zoomed as far as they would scroll:
+        egui_mq.set_wheel_options(crate::WheelOptions {
+            unit: crate::WheelUnit::Line,
+            ctrl_zooms: true,
+            ..options
+        });
+        let line_speed = egui_mq.egui_ctx().options(|o| o.line_scroll_speed);
+        assert_eq!(
+            wheel_events(&mut egui_mq, egui::Modifiers::COMMAND, 0.0, 1.0),
+            [egui::Event::Zoom((zoom_speed * line_speed).exp())]
+        );
+    }
+
+    #[test]
+    fn clipboard_requests_arrive_next_frame() {
+        let (mut backend, _clipboard, mut egui_mq) = egui_mq_with_clipboard(Some("pasted"));
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            egui_ctx.send_viewport_cmd(egui::ViewportCommand::RequestCut);
+            egui_ctx.send_viewport_cmd(egui::ViewportCommand::RequestCopy);
+            egui_ctx.send_viewport_cmd(egui::ViewportCommand::RequestPaste);
+        });
+        assert!(egui_mq.needs_repaint());
+        let events = run_frame(&mut egui_mq, &mut backend, |_| {});
+        assert_eq!(
+            events,
+            [
+                egui::Event::Cut,
+                egui::Event::Copy,
+                egui::Event::Paste("pasted".to_owned()),
+            ]
+        );
+        assert!(egui_mq.unsupported_commands.is_empty());
+    }
+
+    /// An [`crate::EguiMq`] that only uses the 200x100 pixels at (100, 50) of the window.
+    fn egui_mq_in_viewport() -> crate::EguiMq {
+        let (_backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        egui_mq.set_viewport(Some(egui::Rect::from_min_size(
+            egui::pos2(100.0, 50.0),
+            egui::vec2(200.0, 100.0),
+        )));
+        egui_mq
+    }
+
+    fn take_events(egui_mq: &mut crate::EguiMq) -> Vec<egui::Event> {
+        std::mem::take(&mut egui_mq.egui_input.events)
+    }
+
+    #[test]
+    fn viewport_translates_positions() {
+        let mut egui_mq = egui_mq_in_viewport();
+        egui_mq.mouse_motion_event(150.0, 80.0);
+        egui_mq.mouse_button_down_event(miniquad::MouseButton::Left, 150.0, 80.0);
+        assert_eq!(
+            take_events(&mut egui_mq),
+            [
+                egui::Event::PointerMoved(egui::pos2(50.0, 30.0)),
+                primary_button(50.0, 30.0, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn presses_outside_the_viewport_are_ignored() {
+        use miniquad::MouseButton;
+
+        let mut egui_mq = egui_mq_in_viewport();
+        egui_mq.mouse_button_down_event(MouseButton::Left, 10.0, 10.0);
+        egui_mq.mouse_button_up_event(MouseButton::Left, 10.0, 10.0);
+        assert_eq!(take_events(&mut egui_mq), []);
+
+        // Releasing a button pressed outside doesn't end a drag inside:
+        egui_mq.mouse_button_down_event(MouseButton::Left, 10.0, 10.0);
+        egui_mq.mouse_button_down_event(MouseButton::Right, 150.0, 80.0);
+        egui_mq.mouse_button_up_event(MouseButton::Left, 150.0, 80.0);
+        egui_mq.mouse_button_up_event(MouseButton::Right, 150.0, 80.0);
+        let secondary = |pressed| egui::Event::PointerButton {
+            pos: egui::pos2(50.0, 30.0),
+            button: egui::PointerButton::Secondary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+        assert_eq!(
+            take_events(&mut egui_mq),
+            [secondary(true), secondary(false)]
+        );
+    }
+
+    #[test]
+    fn drags_can_end_outside_the_viewport() {
+        use miniquad::MouseButton;
+
+        let mut egui_mq = egui_mq_in_viewport();
+        egui_mq.mouse_button_down_event(MouseButton::Left, 150.0, 80.0);
+        egui_mq.mouse_motion_event(10.0, 10.0);
+        egui_mq.mouse_button_up_event(MouseButton::Left, 10.0, 10.0);
+        assert_eq!(
+            take_events(&mut egui_mq),
+            [
+                primary_button(50.0, 30.0, true),
+                egui::Event::PointerMoved(egui::pos2(-90.0, -40.0)),
+                primary_button(-90.0, -40.0, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn leaving_the_viewport_stops_the_wheel() {
+        let mut egui_mq = egui_mq_in_viewport();
+        egui_mq.set_wheel_options(crate::WheelOptions {
+            unit: crate::WheelUnit::Point,
+            ..Default::default()
+        });
+        egui_mq.mouse_motion_event(150.0, 80.0);
+        egui_mq.mouse_motion_event(10.0, 10.0);
+        egui_mq.mouse_wheel_event(0.0, 1.0);
+        assert_eq!(
+            take_events(&mut egui_mq),
+            [
+                egui::Event::PointerMoved(egui::pos2(50.0, 30.0)),
+                egui::Event::PointerGone,
+            ]
+        );
+
+        egui_mq.mouse_motion_event(150.0, 80.0);
+        egui_mq.mouse_wheel_event(0.0, 1.0);
+        assert_eq!(
+            take_events(&mut egui_mq),
+            [
+                egui::Event::PointerMoved(egui::pos2(50.0, 30.0)),
+                wheel(egui::Modifiers::NONE, 0.0, 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn dropped_files() {
+        let (mut backend, platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        platform.borrow_mut().dropped_file_paths = vec!["/home/user/notes.txt".into()];
+        egui_mq.files_dropped_event();
+        assert!(egui_mq.needs_repaint());
+
+        let mut dropped_files = Vec::new();
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            dropped_files = egui_ctx.input(|i| i.raw.dropped_files.clone());
+        });
+        assert_eq!(dropped_files.len(), 1);
+        assert_eq!(
+            dropped_files[0].path.as_deref(),
+            Some(std::path::Path::new("/home/user/notes.txt"))
+        );
+        assert_eq!(dropped_files[0].name, "notes.txt");
+        assert!(dropped_files[0].bytes.is_none());
+    }
+
+    #[test]
+    fn dropped_bytes_without_a_path() {
+        let (mut backend, platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        // As on the web, where miniquad may only have the contents:
+        platform.borrow_mut().dropped_file_bytes = vec![vec![1, 2, 3]];
+        egui_mq.files_dropped_event();
+
+        let mut dropped_files = Vec::new();
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            dropped_files = egui_ctx.input(|i| i.raw.dropped_files.clone());
+        });
+        assert_eq!(dropped_files.len(), 1);
+        assert_eq!(dropped_files[0].path, None);
+        assert_eq!(dropped_files[0].name, "");
+        assert_eq!(dropped_files[0].bytes.as_deref(), Some(&[1, 2, 3][..]));
+    }
+
+    #[test]
+    fn viewport_commands_reach_the_platform() {
+        let (mut backend, platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        platform.borrow_mut().dpi_scale = 2.0;
+        run_frame(&mut egui_mq, &mut backend, |_| {});
+
+        let mut send = |command: egui::ViewportCommand| {
+            run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+                egui_ctx.send_viewport_cmd(command.clone());
+            });
+        };
+        send(egui::ViewportCommand::Fullscreen(true));
+        assert!(platform.borrow().fullscreen);
+        send(egui::ViewportCommand::Fullscreen(false));
+        assert!(!platform.borrow().fullscreen);
+
+        // In points, so twice as many pixels:
+        send(egui::ViewportCommand::InnerSize(egui::vec2(300.0, 200.0)));
+        assert_eq!(platform.borrow().screen_size, (600.0, 400.0));
+
+        send(egui::ViewportCommand::CursorGrab(
+            egui::viewport::CursorGrab::Confined,
+        ));
+        assert!(platform.borrow().cursor_grabbed);
+        send(egui::ViewportCommand::CursorGrab(
+            egui::viewport::CursorGrab::None,
+        ));
+        assert!(!platform.borrow().cursor_grabbed);
+
+        send(egui::ViewportCommand::CursorVisible(false));
+        assert!(!platform.borrow().mouse_shown);
+        send(egui::ViewportCommand::CursorVisible(true));
+        assert!(platform.borrow().mouse_shown);
+
+        send(egui::ViewportCommand::Close);
+        assert!(platform.borrow().quit_requested);
+    }
+
+    #[test]
+    fn unsupported_commands_are_reported_once() {
+        let (mut backend, _platform, mut egui_mq) = test_egui_mq(Backend::OpenGl);
+        for title in ["a", "b"] {
+            run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+                egui_ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.to_owned()));
+                egui_ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            });
+        }
+        assert_eq!(
+            egui_mq.unsupported_commands,
+            [
+                std::mem::discriminant(&egui::ViewportCommand::Title(String::new())),
+                std::mem::discriminant(&egui::ViewportCommand::Focus),
+            ]
+        );
+    }
+
+    #[test]
+    fn copied_images_go_to_the_clipboard() {
+        let (mut backend, clipboard, mut egui_mq) = egui_mq_with_clipboard(Some("text"));
+        let image = egui::ColorImage::new([2, 1], egui::Color32::RED);
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            egui_ctx.copy_image(image.clone());
+        });
+        let clipboard = clipboard.borrow();
+        assert_eq!(clipboard.image.as_ref(), Some(&image));
+        assert_eq!(clipboard.text, None);
+    }
+
+    #[test]
+    fn failed_image_copies_go_to_the_callback() {
+        struct TextOnly;
+
+        impl crate::ClipboardProvider for TextOnly {
+            fn get(&mut self) -> Option<String> {
+                None
+            }
+
+            fn set(&mut self, _text: &str) {}
+        }
+
+        let mut backend = crate::headless::RecordingBackend::new(Backend::OpenGl);
+        let mut egui_mq = crate::EguiMqBuilder::new()
+            .platform(crate::headless::HeadlessPlatform::new(800.0, 600.0))
+            .clipboard(TextOnly)
+            .build(&mut backend);
+        let errors = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let callback_errors = errors.clone();
+        egui_mq.set_clipboard_error_callback(move |err| callback_errors.borrow_mut().push(err));
+
+        run_frame(&mut egui_mq, &mut backend, |egui_ctx| {
+            egui_ctx.copy_image(egui::ColorImage::new([1, 1], egui::Color32::RED));
+        });
+        assert_eq!(*errors.borrow(), [crate::ClipboardError::Unsupported]);
+    }
+
+    #[test]
+    fn shared_painter_keeps_textures_apart() {
+        let mut backend = crate::headless::RecordingBackend::new(Backend::OpenGl);
+        let shared_painter = crate::SharedPainter::new(&mut backend);
+        let mut egui_mqs: Vec<_> = (0..2)
+            .map(|_| {
+                crate::EguiMqBuilder::new()
+                    .platform(crate::headless::HeadlessPlatform::new(800.0, 600.0))
+                    .shared_painter(shared_painter.clone())
+                    .build(&mut backend)
+            })
+            .collect();
+        for egui_mq in &mut egui_mqs {
+            run_frame(egui_mq, &mut backend, |egui_ctx| {
+                egui::CentralPanel::default().show(egui_ctx, |ui| ui.label("Hello World!"));
+            });
+        }
+
+        let commands = backend.take_commands();
+        let count = |f: fn(&Command) -> bool| commands.iter().filter(|c| f(c)).count();
+        assert_eq!(count(|c| matches!(c, Command::NewShader { .. })), 1);
+        assert_eq!(count(|c| matches!(c, Command::NewPipeline(_))), 1);
+
+        // Each has its own font atlas, which is what it draws with:
+        let font_textures: Vec<_> = commands
+            .iter()
+            .filter_map(|c| match c {
+                Command::NewTexture { texture, width, .. } if *width > 1 => Some(*texture),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(font_textures.len(), 2);
+        let drawn_textures: Vec<_> = commands
+            .iter()
+            .filter_map(|c| match c {
+                Command::ApplyBindings { images, .. } => Some(images[0]),
+                _ => None,
+            })
+            .collect();
+        assert!(drawn_textures.contains(&font_textures[0]));
+        assert!(drawn_textures.contains(&font_textures[1]));
+    }
+}
@@ -1,8 +1,10 @@
+use std::{cell::RefCell, ops::Range, rc::Rc};
+
 use egui::epaint::Vertex;
 use miniquad::{
-    Backend, Bindings, BlendFactor, BlendState, BlendValue, BufferLayout, BufferSource, BufferType,
-    BufferUsage, Equation, Pipeline, PipelineParams, RawId, RenderingBackend, ShaderSource,
-    TextureId, UniformsSource, VertexAttribute, VertexFormat,
+    Backend, Bindings, BlendFactor, BlendState, BlendValue, BufferId, BufferLayout, BufferSource,
+    BufferType, BufferUsage, Equation, PassAction, Pipeline, PipelineParams, RawId, RenderPass,
+    RenderingBackend, ShaderSource, TextureId, UniformsSource, VertexAttribute, VertexFormat,
 };
 
 /// A callback function that can be used to compose an [`egui::PaintCallback`] for custom rendering
@@ -28,64 +30,90 @@ impl CallbackFn {
     }
 }
 
+/// Options for drawing egui with miniquad, see [`crate::EguiMqBuilder::painter_options`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PainterOptions {
+    /// Filter all textures egui creates with this, instead of the filter egui asks for.
+    ///
+    /// E.g. [`egui::TextureFilter::Nearest`] for crisp pixel art fonts.
+    pub texture_filter: Option<egui::TextureFilter>,
+}
+
+/// The pipeline and buffers egui is drawn with, shared by several [`crate::EguiMq`].
+///
+/// Each [`crate::EguiMq`] still has its own textures, since every egui context has its own
+/// font atlas. Pass a clone to [`crate::EguiMqBuilder::shared_painter`].
+///
+/// The painter is busy while drawing, so an [`egui::PaintCallback`] can't draw another
+/// [`crate::EguiMq`] with the same painter. That one is skipped, with a message on stderr.
+#[derive(Clone)]
+pub struct SharedPainter(Rc<RefCell<PainterCore>>);
+
+impl SharedPainter {
+    pub fn new(ctx: &mut dyn RenderingBackend) -> Self {
+        Self(Rc::new(RefCell::new(PainterCore::new(ctx))))
+    }
+}
+
 pub struct Painter {
-    pipeline: Pipeline,
-    bindings: Bindings,
+    options: PainterOptions,
+    shared: SharedPainter,
     textures: std::collections::HashMap<egui::TextureId, miniquad::TextureId>,
 }
 
-impl Painter {
-    pub fn new(ctx: &mut dyn RenderingBackend) -> Painter {
-        let source = match ctx.info().backend {
-            Backend::Metal => unimplemented!(),
-            Backend::OpenGl => ShaderSource::Glsl {
-                vertex: shader::VERTEX,
-                fragment: shader::FRAGMENT,
-            },
-        };
-        let shader = ctx.new_shader(source, shader::meta());
-
-        let pipeline = ctx.new_pipeline(
-            &[BufferLayout::default()],
-            &[
-                VertexAttribute::new("a_pos", VertexFormat::Float2),
-                VertexAttribute::new("a_tc", VertexFormat::Float2),
-                VertexAttribute::new("a_srgba", VertexFormat::Byte4),
-            ],
-            shader.expect("couldn't make shader"),
-            PipelineParams {
-                color_blend: Some(BlendState::new(
-                    Equation::Add,
-                    BlendFactor::One,
-                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
-                )),
-                cull_face: miniquad::CullFace::Nothing,
-                ..Default::default()
-            },
-        );
+struct PainterCore {
+    backend: Backend,
+    /// If false, meshes are split into `u16`-indexed pieces before drawing.
+    u32_indices: bool,
+    /// If false, every mesh gets its own [`StreamBuffers`],
+    /// since the backend can't start drawing in the middle of an index buffer.
+    draw_with_offset: bool,
+    pipeline: Pipeline,
+    bindings: Bindings,
+    /// One pair of buffers per [`Batch`], reused between frames.
+    stream_buffers: Vec<StreamBuffers>,
+    /// The vertices of all meshes of the frame, batch after batch.
+    vertices: Vec<Vertex>,
+    /// The indices of all meshes of the frame, relative to the start of their batch.
+    indices: Vec<u32>,
+    /// Scratch space for uploading `indices` when `u32_indices` is false.
+    indices_u16: Vec<u16>,
+    batches: Vec<Batch>,
+}
 
-        let vertex_buffer = ctx.new_buffer(
-            BufferType::VertexBuffer,
-            BufferUsage::Stream,
-            BufferSource::empty::<Vertex>(32 * 1024),
-        );
-        let index_buffer = ctx.new_buffer(
-            BufferType::IndexBuffer,
-            BufferUsage::Stream,
-            BufferSource::empty::<u16>(32 * 1024),
-        );
+/// Vertex and index buffer that a [`Batch`] is uploaded to.
+struct StreamBuffers {
+    vertex_buffer: BufferId,
+    index_buffer: BufferId,
+}
 
-        let white_texture = ctx.new_texture_from_rgba8(1, 1, &[255, 255, 255, 255]);
+/// Ranges of [`PainterCore::vertices`] and [`PainterCore::indices`]
+/// that are uploaded with one `buffer_update` each.
+struct Batch {
+    vertices: Range<usize>,
+    indices: Range<usize>,
+}
 
-        let bindings = Bindings {
-            vertex_buffers: vec![vertex_buffer],
-            index_buffer,
-            images: vec![white_texture],
-        };
+/// What to do once all batches of the frame are uploaded.
+enum PaintJob {
+    Mesh {
+        clip_rect: egui::Rect,
+        texture_id: egui::TextureId,
+        batch: usize,
+        /// Relative to the start of the batch.
+        indices: Range<usize>,
+    },
+    Callback {
+        clip_rect: egui::Rect,
+        callback: egui::PaintCallback,
+    },
+}
 
+impl Painter {
+    pub fn new(shared: SharedPainter, options: PainterOptions) -> Painter {
         Painter {
-            pipeline,
-            bindings,
+            options,
+            shared,
             textures: Default::default(),
         }
     }
@@ -97,7 +125,11 @@ impl Painter {
         delta: &egui::epaint::ImageDelta,
     ) {
         let [w, h] = delta.image.size();
-        let filter = match delta.options.magnification {
+        let filter = match self
+            .options
+            .texture_filter
+            .unwrap_or(delta.options.magnification)
+        {
             egui::TextureFilter::Nearest => miniquad::FilterMode::Nearest,
             egui::TextureFilter::Linear => miniquad::FilterMode::Linear,
         };
@@ -184,9 +216,15 @@ impl Painter {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn paint_and_update_textures(
         &mut self,
         ctx: &mut dyn RenderingBackend,
+        pass: Option<RenderPass>,
+        action: PassAction,
+        target_size_in_pixels: (u32, u32),
+        window_height_in_pixels: f32,
+        viewport: Option<egui::Rect>,
         primtives: Vec<egui::ClippedPrimitive>,
         textures_delta: &egui::TexturesDelta,
         egui_ctx: &egui::Context,
@@ -195,53 +233,235 @@ impl Painter {
             self.set_texture(ctx, *id, image_delta);
         }
 
-        self.paint(ctx, primtives, egui_ctx);
+        self.paint(
+            ctx,
+            pass,
+            action,
+            target_size_in_pixels,
+            window_height_in_pixels,
+            viewport,
+            primtives,
+            egui_ctx,
+        );
 
         for &id in &textures_delta.free {
             self.free_texture(ctx, id);
         }
     }
 
+    /// Paint the primitives into `pass` (`None` is the default framebuffer),
+    /// which must be `target_size_in_pixels` large.
+    ///
+    /// Metal flips scissor rects with the height of the window, even in offscreen passes,
+    /// so that needs to be known too.
+    ///
+    /// If `viewport` is set, egui is drawn into that part of the target (in physical pixels)
+    /// as if it were the whole screen.
+    #[allow(clippy::too_many_arguments)]
     pub fn paint(
         &mut self,
         ctx: &mut dyn RenderingBackend,
+        pass: Option<RenderPass>,
+        action: PassAction,
+        target_size_in_pixels: (u32, u32),
+        window_height_in_pixels: f32,
+        viewport: Option<egui::Rect>,
         primtives: Vec<egui::ClippedPrimitive>,
         egui_ctx: &egui::Context,
     ) {
-        ctx.begin_default_pass(miniquad::PassAction::Nothing);
-        ctx.apply_pipeline(&self.pipeline);
+        match self.shared.0.try_borrow_mut() {
+            Ok(mut core) => core.paint(
+                ctx,
+                pass,
+                action,
+                target_size_in_pixels,
+                window_height_in_pixels,
+                viewport,
+                primtives,
+                egui_ctx,
+                &self.textures,
+            ),
+            Err(_) => eprintln!(
+                "egui-miniquad: Can't draw egui from a paint callback that uses the same SharedPainter"
+            ),
+        }
+    }
+}
+
+impl PainterCore {
+    fn new(ctx: &mut dyn RenderingBackend) -> PainterCore {
+        let backend = ctx.info().backend;
+        let source = match backend {
+            Backend::Metal => ShaderSource::Msl {
+                program: shader::METAL,
+            },
+            Backend::OpenGl => ShaderSource::Glsl {
+                vertex: shader::VERTEX,
+                fragment: shader::FRAGMENT,
+            },
+        };
+        let shader = ctx.new_shader(source, shader::meta());
 
-        let screen_size_in_pixels = miniquad::window::screen_size();
+        let pipeline = ctx.new_pipeline(
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("a_pos", VertexFormat::Float2),
+                VertexAttribute::new("a_tc", VertexFormat::Float2),
+                VertexAttribute::new("a_srgba", VertexFormat::Byte4),
+            ],
+            shader.expect("couldn't make shader"),
+            PipelineParams {
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::One,
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                cull_face: miniquad::CullFace::Nothing,
+                ..Default::default()
+            },
+        );
+
+        let u32_indices = supports_u32_indices(&ctx.info());
+        let draw_with_offset = backend == Backend::OpenGl;
+
+        let stream_buffers = StreamBuffers::new(ctx, u32_indices, 32 * 1024, 32 * 1024);
+
+        let white_texture = ctx.new_texture_from_rgba8(1, 1, &[255, 255, 255, 255]);
+
+        let bindings = Bindings {
+            vertex_buffers: vec![stream_buffers.vertex_buffer],
+            index_buffer: stream_buffers.index_buffer,
+            images: vec![white_texture],
+        };
+
+        PainterCore {
+            backend,
+            u32_indices,
+            draw_with_offset,
+            pipeline,
+            bindings,
+            stream_buffers: vec![stream_buffers],
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            indices_u16: Vec::new(),
+            batches: Vec::new(),
+        }
+    }
+
+    /// The geometry of all meshes is uploaded up front, with as few `buffer_update` calls as
+    /// the backend allows (one per frame on desktop OpenGL), and then drawn mesh by mesh.
+    #[allow(clippy::too_many_arguments)]
+    fn paint(
+        &mut self,
+        ctx: &mut dyn RenderingBackend,
+        pass: Option<RenderPass>,
+        action: PassAction,
+        target_size_in_pixels: (u32, u32),
+        window_height_in_pixels: f32,
+        viewport: Option<egui::Rect>,
+        primtives: Vec<egui::ClippedPrimitive>,
+        egui_ctx: &egui::Context,
+        textures: &std::collections::HashMap<egui::TextureId, miniquad::TextureId>,
+    ) {
+        let pixels_per_point = egui_ctx.pixels_per_point();
+
+        // Rather than using a miniquad viewport (which the Metal backend ignores),
+        // move the geometry into the viewport and clip it there.
+        let (offset, viewport_clip) = match viewport {
+            Some(viewport) => (
+                viewport.min.to_vec2() / pixels_per_point,
+                egui::Rect::from_min_max(
+                    viewport.min / pixels_per_point,
+                    viewport.max / pixels_per_point,
+                ),
+            ),
+            None => (egui::Vec2::ZERO, egui::Rect::EVERYTHING),
+        };
+
+        let jobs = self.batch_primitives(primtives, offset, viewport_clip);
+        self.upload_batches(ctx);
+
+        ctx.begin_pass(pass, action);
+
+        let screen_size_in_pixels = (
+            target_size_in_pixels.0 as f32,
+            target_size_in_pixels.1 as f32,
+        );
+        // miniquad turns our bottom-left scissor rects top-left again on Metal,
+        // using the height of the window rather than of the target.
+        let scissor_flip_height = match self.backend {
+            Backend::Metal => window_height_in_pixels,
+            Backend::OpenGl => screen_size_in_pixels.1,
+        };
         let screen_size_in_points = (
-            screen_size_in_pixels.0 / egui_ctx.pixels_per_point(),
-            screen_size_in_pixels.1 / egui_ctx.pixels_per_point(),
+            screen_size_in_pixels.0 / pixels_per_point,
+            screen_size_in_pixels.1 / pixels_per_point,
         );
-        ctx.apply_uniforms(UniformsSource::table(&shader::Uniforms {
+        let uniforms = shader::Uniforms {
             u_screen_size: screen_size_in_points,
-        }));
+        };
+        ctx.apply_pipeline(&self.pipeline);
+        ctx.apply_uniforms(UniformsSource::table(&uniforms));
 
-        for egui::ClippedPrimitive {
-            clip_rect,
-            primitive,
-        } in primtives
-        {
-            match primitive {
-                egui::epaint::Primitive::Mesh(mesh) => {
-                    self.paint_job(ctx, clip_rect, mesh, egui_ctx);
+        for job in jobs {
+            match job {
+                PaintJob::Mesh {
+                    clip_rect,
+                    texture_id,
+                    batch,
+                    indices,
+                } => {
+                    self.bindings.images[0] = match texture_id {
+                        egui::TextureId::Managed(id) => {
+                            if let Some(tex) = textures.get(&texture_id) {
+                                *tex
+                            } else {
+                                eprintln!("Texture {id:?} not found");
+                                continue;
+                            }
+                        }
+                        egui::TextureId::User(id) => {
+                            if let Some(tex) = user_texture(self.backend, id) {
+                                tex
+                            } else {
+                                eprintln!(
+                                    "User texture {id} can't be drawn with {:?}",
+                                    self.backend
+                                );
+                                continue;
+                            }
+                        }
+                    };
+                    self.bindings.vertex_buffers[0] = self.stream_buffers[batch].vertex_buffer;
+                    self.bindings.index_buffer = self.stream_buffers[batch].index_buffer;
+
+                    apply_clip_rect(
+                        ctx,
+                        screen_size_in_pixels,
+                        scissor_flip_height,
+                        pixels_per_point,
+                        clip_rect,
+                    );
+                    ctx.apply_bindings(&self.bindings);
+                    ctx.draw(indices.start as i32, indices.len() as i32, 1);
                 }
-                egui::epaint::Primitive::Callback(callback) => {
+                PaintJob::Callback {
+                    clip_rect,
+                    callback,
+                } => {
                     let info = egui::PaintCallbackInfo {
                         viewport: callback.rect,
                         clip_rect,
-                        pixels_per_point: egui_ctx.pixels_per_point(),
-                        screen_size_px: [
-                            screen_size_in_pixels.0.round() as _,
-                            screen_size_in_pixels.1.round() as _,
-                        ],
+                        pixels_per_point,
+                        screen_size_px: [target_size_in_pixels.0, target_size_in_pixels.1],
                     };
 
                     if let Some(callback) = callback.callback.downcast_ref::<CallbackFn>() {
                         (callback.f)(info, ctx);
+
+                        // The callback may have used its own pipeline:
+                        ctx.apply_pipeline(&self.pipeline);
+                        ctx.apply_uniforms(UniformsSource::table(&uniforms));
                     } else {
                         eprintln!(
                             "Warning: Unsupported render callback. Expected egui_miniquad::CallbackFn"
@@ -254,90 +474,265 @@ impl Painter {
         ctx.end_render_pass();
     }
 
-    pub fn paint_job(
+    /// Collect the geometry of all meshes into `self.vertices` and `self.indices`,
+    /// moved by `offset` and clipped to `viewport_clip`.
+    fn batch_primitives(
+        &mut self,
+        primtives: Vec<egui::ClippedPrimitive>,
+        offset: egui::Vec2,
+        viewport_clip: egui::Rect,
+    ) -> Vec<PaintJob> {
+        self.vertices.clear();
+        self.indices.clear();
+        self.batches.clear();
+
+        let mut jobs = Vec::with_capacity(primtives.len());
+        for egui::ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in primtives
+        {
+            let clip_rect = clip_rect.translate(offset).intersect(viewport_clip);
+            match primitive {
+                egui::epaint::Primitive::Mesh(mesh) => {
+                    assert!(mesh.is_valid());
+                    if self.u32_indices {
+                        jobs.extend(self.add_mesh(
+                            clip_rect,
+                            offset,
+                            mesh.texture_id,
+                            &mesh.vertices,
+                            &mesh.indices,
+                        ));
+                    } else {
+                        for mesh in mesh.split_to_u16() {
+                            jobs.extend(self.add_mesh(
+                                clip_rect,
+                                offset,
+                                mesh.texture_id,
+                                &mesh.vertices,
+                                &mesh.indices,
+                            ));
+                        }
+                    }
+                }
+                egui::epaint::Primitive::Callback(mut callback) => {
+                    callback.rect = callback.rect.translate(offset);
+                    jobs.push(PaintJob::Callback {
+                        clip_rect,
+                        callback,
+                    });
+                }
+            }
+        }
+        jobs
+    }
+
+    fn add_mesh<I: Copy + Into<u32>>(
         &mut self,
-        ctx: &mut dyn RenderingBackend,
         clip_rect: egui::Rect,
-        mesh: egui::epaint::Mesh,
-        egui_ctx: &egui::Context,
-    ) {
-        let screen_size_in_pixels = miniquad::window::screen_size();
-        let pixels_per_point = egui_ctx.pixels_per_point();
+        offset: egui::Vec2,
+        texture_id: egui::TextureId,
+        vertices: &[Vertex],
+        indices: &[I],
+    ) -> Option<PaintJob> {
+        if indices.is_empty() {
+            return None;
+        }
 
-        // TODO: support u32 indices in miniquad and just use "mesh.indices" without a need for `split_to_u16`
-        let meshes = mesh.split_to_u16();
-        for mesh in meshes {
-            assert!(mesh.is_valid());
-            let vertices_size_bytes = mesh.vertices.len() * std::mem::size_of::<Vertex>();
-            if ctx.buffer_size(self.bindings.vertex_buffers[0]) < vertices_size_bytes {
-                ctx.delete_buffer(self.bindings.vertex_buffers[0]);
-                self.bindings.vertex_buffers[0] = ctx.new_buffer(
-                    BufferType::VertexBuffer,
-                    BufferUsage::Stream,
-                    BufferSource::empty::<Vertex>(mesh.vertices.len()),
-                );
+        let max_vertices_per_batch = if self.u32_indices {
+            u32::MAX as usize
+        } else {
+            u16::MAX as usize + 1
+        };
+        let fits_in_last_batch = self.batches.last().map_or(false, |batch| {
+            self.draw_with_offset && batch.vertices.len() + vertices.len() <= max_vertices_per_batch
+        });
+        if !fits_in_last_batch {
+            self.batches.push(Batch {
+                vertices: self.vertices.len()..self.vertices.len(),
+                indices: self.indices.len()..self.indices.len(),
+            });
+        }
+
+        let batch_index = self.batches.len() - 1;
+        let batch = &mut self.batches[batch_index];
+        let base_vertex = batch.vertices.len() as u32;
+        let first_index = batch.indices.len();
+
+        let first_vertex = self.vertices.len();
+        self.vertices.extend_from_slice(vertices);
+        if offset != egui::Vec2::ZERO {
+            for vertex in &mut self.vertices[first_vertex..] {
+                vertex.pos += offset;
             }
-            ctx.buffer_update(
-                self.bindings.vertex_buffers[0],
-                BufferSource::slice(&mesh.vertices),
-            );
+        }
+        self.indices
+            .extend(indices.iter().map(|&index| base_vertex + index.into()));
+        batch.vertices.end = self.vertices.len();
+        batch.indices.end = self.indices.len();
+
+        Some(PaintJob::Mesh {
+            clip_rect,
+            texture_id,
+            batch: batch_index,
+            indices: first_index..first_index + indices.len(),
+        })
+    }
+
+    fn upload_batches(&mut self, ctx: &mut dyn RenderingBackend) {
+        for (i, batch) in self.batches.iter().enumerate() {
+            let vertices = &self.vertices[batch.vertices.clone()];
+            let indices = &self.indices[batch.indices.clone()];
+
+            if let Some(stream_buffers) = self.stream_buffers.get_mut(i) {
+                stream_buffers.reserve(ctx, self.u32_indices, vertices.len(), indices.len());
+            } else {
+                self.stream_buffers.push(StreamBuffers::new(
+                    ctx,
+                    self.u32_indices,
+                    vertices.len(),
+                    indices.len(),
+                ));
+            }
+            let stream_buffers = &self.stream_buffers[i];
 
-            let indices_size_bytes = mesh.indices.len() * std::mem::size_of::<u16>();
-            if ctx.buffer_size(self.bindings.index_buffer) < indices_size_bytes {
-                ctx.delete_buffer(self.bindings.index_buffer);
-                self.bindings.index_buffer = ctx.new_buffer(
-                    BufferType::IndexBuffer,
-                    BufferUsage::Stream,
-                    BufferSource::empty::<u16>(mesh.indices.len()),
+            ctx.buffer_update(stream_buffers.vertex_buffer, BufferSource::slice(vertices));
+            if self.u32_indices {
+                ctx.buffer_update(stream_buffers.index_buffer, BufferSource::slice(indices));
+            } else {
+                self.indices_u16.clear();
+                self.indices_u16
+                    .extend(indices.iter().map(|&index| index as u16));
+                ctx.buffer_update(
+                    stream_buffers.index_buffer,
+                    BufferSource::slice(&self.indices_u16),
                 );
             }
-            ctx.buffer_update(
-                self.bindings.index_buffer,
-                BufferSource::slice(&mesh.indices),
-            );
+        }
+    }
+}
 
-            self.bindings.images[0] = match mesh.texture_id {
-                egui::TextureId::Managed(id) => {
-                    if let Some(tex) = self.textures.get(&mesh.texture_id) {
-                        *tex
-                    } else {
-                        eprintln!("Texture {id:?} not found");
-                        continue;
-                    }
-                }
-                egui::TextureId::User(id) => TextureId::from_raw_id(RawId::OpenGl(id as _)),
-            };
+impl StreamBuffers {
+    fn new(
+        ctx: &mut dyn RenderingBackend,
+        u32_indices: bool,
+        vertex_count: usize,
+        index_count: usize,
+    ) -> Self {
+        let vertex_buffer = ctx.new_buffer(
+            BufferType::VertexBuffer,
+            BufferUsage::Stream,
+            BufferSource::empty::<Vertex>(vertex_count),
+        );
+        let index_buffer = if u32_indices {
+            ctx.new_buffer(
+                BufferType::IndexBuffer,
+                BufferUsage::Stream,
+                BufferSource::empty::<u32>(index_count),
+            )
+        } else {
+            ctx.new_buffer(
+                BufferType::IndexBuffer,
+                BufferUsage::Stream,
+                BufferSource::empty::<u16>(index_count),
+            )
+        };
+        StreamBuffers {
+            vertex_buffer,
+            index_buffer,
+        }
+    }
 
-            let (width_in_pixels, height_in_pixels) = screen_size_in_pixels;
+    /// Grow the buffers if they can't hold this many vertices and indices.
+    fn reserve(
+        &mut self,
+        ctx: &mut dyn RenderingBackend,
+        u32_indices: bool,
+        vertex_count: usize,
+        index_count: usize,
+    ) {
+        let index_size = if u32_indices {
+            std::mem::size_of::<u32>()
+        } else {
+            std::mem::size_of::<u16>()
+        };
+        if ctx.buffer_size(self.vertex_buffer) < vertex_count * std::mem::size_of::<Vertex>()
+            || ctx.buffer_size(self.index_buffer) < index_count * index_size
+        {
+            ctx.delete_buffer(self.vertex_buffer);
+            ctx.delete_buffer(self.index_buffer);
+            *self = StreamBuffers::new(ctx, u32_indices, vertex_count, index_count);
+        }
+    }
+}
+
+/// Whether meshes can be drawn with `u32` indices, rather than split up for `u16` ones.
+///
+/// miniquad's Metal backend only draws `u16` indices, OpenGL ES 2 (e.g. on Android) only has
+/// `u32` ones with an extension, and WebGL 1 has the same extension but miniquad doesn't enable it.
+fn supports_u32_indices(info: &miniquad::ContextInfo) -> bool {
+    let version = &info.gl_version_string;
+    match info.backend {
+        Backend::Metal => false,
+        Backend::OpenGl if version.contains("WebGL") => false,
+        Backend::OpenGl if version.contains("OpenGL ES") => version.contains("OpenGL ES 3"),
+        // An empty version is what miniquad takes for OpenGL 2, which has them.
+        Backend::OpenGl => true,
+    }
+}
 
-            // From https://github.com/emilk/egui/blob/master/egui_glium/src/painter.rs#L233
+/// Set the scissor rect to `clip_rect`, which is in points.
+///
+/// miniquad wants the rect from the bottom left, of something `flip_height` pixels high.
+fn apply_clip_rect(
+    ctx: &mut dyn RenderingBackend,
+    screen_size_in_pixels: (f32, f32),
+    flip_height: f32,
+    pixels_per_point: f32,
+    clip_rect: egui::Rect,
+) {
+    let (width_in_pixels, height_in_pixels) = screen_size_in_pixels;
 
-            // Transform clip rect to physical pixels:
-            let clip_min_x = pixels_per_point * clip_rect.min.x;
-            let clip_min_y = pixels_per_point * clip_rect.min.y;
-            let clip_max_x = pixels_per_point * clip_rect.max.x;
-            let clip_max_y = pixels_per_point * clip_rect.max.y;
+    // From https://github.com/emilk/egui/blob/master/egui_glium/src/painter.rs#L233
 
-            // Make sure clip rect can fit withing an `u32`:
-            let clip_min_x = clip_min_x.clamp(0.0, width_in_pixels);
-            let clip_min_y = clip_min_y.clamp(0.0, height_in_pixels);
-            let clip_max_x = clip_max_x.clamp(clip_min_x, width_in_pixels);
-            let clip_max_y = clip_max_y.clamp(clip_min_y, height_in_pixels);
+    // Transform clip rect to physical pixels:
+    let clip_min_x = pixels_per_point * clip_rect.min.x;
+    let clip_min_y = pixels_per_point * clip_rect.min.y;
+    let clip_max_x = pixels_per_point * clip_rect.max.x;
+    let clip_max_y = pixels_per_point * clip_rect.max.y;
 
-            let clip_min_x = clip_min_x.round() as u32;
-            let clip_min_y = clip_min_y.round() as u32;
-            let clip_max_x = clip_max_x.round() as u32;
-            let clip_max_y = clip_max_y.round() as u32;
+    // Make sure clip rect can fit withing an `u32`:
+    let clip_min_x = clip_min_x.clamp(0.0, width_in_pixels);
+    let clip_min_y = clip_min_y.clamp(0.0, height_in_pixels);
+    let clip_max_x = clip_max_x.clamp(clip_min_x, width_in_pixels);
+    let clip_max_y = clip_max_y.clamp(clip_min_y, height_in_pixels);
 
-            ctx.apply_scissor_rect(
-                clip_min_x as i32,
-                (height_in_pixels as u32 - clip_max_y) as i32,
-                (clip_max_x - clip_min_x) as i32,
-                (clip_max_y - clip_min_y) as i32,
-            );
-            ctx.apply_bindings(&self.bindings);
-            ctx.draw(0, mesh.indices.len() as i32, 1);
-        }
+    let clip_min_x = clip_min_x.round() as u32;
+    let clip_min_y = clip_min_y.round() as u32;
+    let clip_max_x = clip_max_x.round() as u32;
+    let clip_max_y = clip_max_y.round() as u32;
+
+    ctx.apply_scissor_rect(
+        clip_min_x as i32,
+        flip_height.round() as i32 - clip_max_y as i32,
+        (clip_max_x - clip_min_x) as i32,
+        (clip_max_y - clip_min_y) as i32,
+    );
+}
+
+/// Wrap a [`egui::TextureId::User`] id, which holds the raw id of a native texture
+/// (an OpenGL texture name or a pointer to a `MTLTexture`), into a miniquad texture.
+///
+/// `None` for Metal outside of Apple platforms, where miniquad has no Metal ids
+/// (and no Metal backend, unless faked in tests).
+fn user_texture(backend: Backend, id: u64) -> Option<TextureId> {
+    match backend {
+        Backend::OpenGl => Some(TextureId::from_raw_id(RawId::OpenGl(id as _))),
+        #[cfg(target_vendor = "apple")]
+        Backend::Metal => Some(TextureId::from_raw_id(RawId::Metal(id as _))),
+        #[cfg(not(target_vendor = "apple"))]
+        Backend::Metal => None,
     }
 }
 
@@ -380,6 +775,57 @@ mod shader {
     }
     "#;
 
+    pub const METAL: &str = r#"
+    #include <metal_stdlib>
+
+    using namespace metal;
+
+    struct Uniforms
+    {
+        float2 u_screen_size;
+    };
+
+    struct Vertex
+    {
+        float2 a_pos    [[attribute(0)]];
+        float2 a_tc     [[attribute(1)]];
+        uchar4 a_srgba  [[attribute(2)]];
+    };
+
+    struct RasterizerData
+    {
+        float4 position [[position]];
+        float2 v_tc;
+        float4 v_rgba_in_gamma;
+    };
+
+    vertex RasterizerData vertexShader(
+        Vertex v [[stage_in]],
+        constant Uniforms& uniforms [[buffer(0)]])
+    {
+        RasterizerData out;
+
+        out.position = float4(
+            2.0 * v.a_pos.x / uniforms.u_screen_size.x - 1.0,
+            1.0 - 2.0 * v.a_pos.y / uniforms.u_screen_size.y,
+            0.0,
+            1.0);
+        out.v_rgba_in_gamma = float4(v.a_srgba) / 255.0;
+        out.v_tc = v.a_tc;
+
+        return out;
+    }
+
+    fragment float4 fragmentShader(
+        RasterizerData in [[stage_in]],
+        texture2d<float> u_sampler [[texture(0)]],
+        sampler u_sampler_smplr [[sampler(0)]])
+    {
+        float4 texture_in_gamma = u_sampler.sample(u_sampler_smplr, in.v_tc);
+        return in.v_rgba_in_gamma * texture_in_gamma;
+    }
+    "#;
+
     pub fn meta() -> ShaderMeta {
         ShaderMeta {
             images: vec!["u_sampler".to_string()],
@@ -395,3 +841,450 @@ mod shader {
         pub u_screen_size: (f32, f32),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{Command, RecordingBackend};
+
+    /// A painter with a white texture for [`egui::TextureId::default`].
+    fn painter(backend: &mut RecordingBackend) -> Painter {
+        let mut painter = Painter::new(SharedPainter::new(backend), PainterOptions::default());
+        let image = egui::ColorImage::new([1, 1], egui::Color32::WHITE);
+        let delta = egui::epaint::ImageDelta::full(image, egui::TextureOptions::LINEAR);
+        painter.set_texture(backend, egui::TextureId::default(), &delta);
+        painter
+    }
+
+    /// A mesh of `quads` unconnected quads.
+    fn mesh(texture_id: egui::TextureId, quads: usize) -> egui::ClippedPrimitive {
+        let mut mesh = egui::Mesh::with_texture(texture_id);
+        for i in 0..quads {
+            let min = egui::pos2((i % 100) as f32, (i / 100) as f32);
+            mesh.add_rect_with_uv(
+                egui::Rect::from_min_size(min, egui::vec2(1.0, 1.0)),
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+        }
+        egui::ClippedPrimitive {
+            clip_rect: egui::Rect::EVERYTHING,
+            primitive: egui::epaint::Primitive::Mesh(mesh),
+        }
+    }
+
+    fn paint(
+        painter: &mut Painter,
+        backend: &mut RecordingBackend,
+        primitives: Vec<egui::ClippedPrimitive>,
+    ) -> Vec<Command> {
+        backend.take_commands();
+        painter.paint(
+            backend,
+            None,
+            PassAction::Nothing,
+            (800, 600),
+            600.0,
+            None,
+            primitives,
+            &egui::Context::default(),
+        );
+        backend.take_commands()
+    }
+
+    #[test]
+    fn metal_pipeline() {
+        let mut backend = RecordingBackend::new(Backend::Metal);
+        let mut painter = painter(&mut backend);
+
+        let commands = backend.take_commands();
+        assert!(commands
+            .iter()
+            .any(|c| matches!(c, Command::NewShader { metal: true, .. })));
+        assert!(commands.iter().any(|c| matches!(
+            c,
+            Command::NewBuffer {
+                type_: BufferType::IndexBuffer,
+                element_size: Some(2),
+                ..
+            }
+        )));
+
+        // A user texture must not panic, even where Metal ids don't exist.
+        let commands = paint(
+            &mut painter,
+            &mut backend,
+            vec![mesh(egui::TextureId::User(7), 1)],
+        );
+        let draws = commands
+            .iter()
+            .filter(|c| matches!(c, Command::Draw { .. }))
+            .count();
+        assert_eq!(draws, usize::from(cfg!(target_vendor = "apple")));
+    }
+
+    #[test]
+    fn opengl_pipeline() {
+        let mut backend = RecordingBackend::new(Backend::OpenGl);
+        let _painter = painter(&mut backend);
+
+        let commands = backend.take_commands();
+        assert!(commands
+            .iter()
+            .any(|c| matches!(c, Command::NewShader { metal: false, .. })));
+        let index_size = if cfg!(target_arch = "wasm32") { 2 } else { 4 };
+        assert!(commands.iter().any(|c| matches!(
+            c,
+            Command::NewBuffer {
+                type_: BufferType::IndexBuffer,
+                element_size: Some(size),
+                ..
+            } if *size == index_size
+        )));
+    }
+
+    fn draws(commands: &[Command]) -> Vec<i32> {
+        commands
+            .iter()
+            .filter_map(|c| match c {
+                Command::Draw { base_element, .. } => Some(*base_element),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn count(commands: &[Command], f: impl Fn(&Command) -> bool) -> usize {
+        commands.iter().filter(|c| f(c)).count()
+    }
+
+    fn three_meshes() -> Vec<egui::ClippedPrimitive> {
+        (0..3)
+            .map(|_| mesh(egui::TextureId::default(), 1))
+            .collect()
+    }
+
+    #[test]
+    fn opengl_uploads_once_per_frame() {
+        let mut backend = RecordingBackend::new(Backend::OpenGl);
+        let mut painter = painter(&mut backend);
+
+        for _ in 0..2 {
+            let commands = paint(&mut painter, &mut backend, three_meshes());
+            // One vertex buffer and one index buffer:
+            assert_eq!(
+                count(&commands, |c| matches!(c, Command::BufferUpdate(_))),
+                2
+            );
+            assert_eq!(
+                count(&commands, |c| matches!(c, Command::NewBuffer { .. })),
+                0
+            );
+            // Each quad has 6 indices:
+            assert_eq!(draws(&commands), vec![0, 6, 12]);
+            assert_eq!(
+                count(&commands, |c| matches!(c, Command::ApplyScissorRect { .. })),
+                3
+            );
+        }
+    }
+
+    #[test]
+    fn metal_uploads_each_mesh_to_its_own_buffers() {
+        let mut backend = RecordingBackend::new(Backend::Metal);
+        let mut painter = painter(&mut backend);
+
+        let commands = paint(&mut painter, &mut backend, three_meshes());
+        assert_eq!(
+            count(&commands, |c| matches!(c, Command::BufferUpdate(_))),
+            6
+        );
+        // The first mesh reuses the buffers made up front:
+        assert_eq!(
+            count(&commands, |c| matches!(c, Command::NewBuffer { .. })),
+            4
+        );
+        assert_eq!(draws(&commands), vec![0, 0, 0]);
+        assert_eq!(
+            count(&commands, |c| matches!(c, Command::ApplyScissorRect { .. })),
+            3
+        );
+
+        let mut vertex_buffers: Vec<_> = commands
+            .iter()
+            .filter_map(|c| match c {
+                Command::ApplyBindings { vertex_buffers, .. } => Some(vertex_buffers[0]),
+                _ => None,
+            })
+            .collect();
+        vertex_buffers.dedup();
+        assert_eq!(vertex_buffers.len(), 3);
+
+        // The buffers are reused next frame:
+        let commands = paint(&mut painter, &mut backend, three_meshes());
+        assert_eq!(
+            count(&commands, |c| matches!(c, Command::NewBuffer { .. })),
+            0
+        );
+    }
+
+    /// Like WebGL 1: `u16` indices, but drawing from the middle of a buffer.
+    fn u16_painter(backend: &mut RecordingBackend) -> Painter {
+        let painter = painter(backend);
+        {
+            let mut core = painter.shared.0.borrow_mut();
+            core.u32_indices = false;
+            core.stream_buffers = vec![StreamBuffers::new(backend, false, 1024, 1024)];
+        }
+        painter
+    }
+
+    #[test]
+    fn u16_batches_hold_65536_vertices() {
+        let mut backend = RecordingBackend::new(Backend::OpenGl);
+        let mut painter = u16_painter(&mut backend);
+        let texture = egui::TextureId::default();
+
+        // 65532 + 4 vertices fill one batch exactly:
+        let commands = paint(
+            &mut painter,
+            &mut backend,
+            vec![mesh(texture, 16383), mesh(texture, 1)],
+        );
+        assert_eq!(
+            count(&commands, |c| matches!(c, Command::BufferUpdate(_))),
+            2
+        );
+        assert_eq!(draws(&commands), vec![0, 16383 * 6]);
+
+        // Four more vertices start a new batch:
+        let commands = paint(
+            &mut painter,
+            &mut backend,
+            vec![mesh(texture, 16383), mesh(texture, 1), mesh(texture, 1)],
+        );
+        assert_eq!(
+            count(&commands, |c| matches!(c, Command::BufferUpdate(_))),
+            4
+        );
+        assert_eq!(draws(&commands), vec![0, 16383 * 6, 0]);
+    }
+
+    #[test]
+    fn u16_splits_large_meshes() {
+        let mut backend = RecordingBackend::new(Backend::OpenGl);
+        let mut split_painter = u16_painter(&mut backend);
+
+        // 65536 vertices don't fit in one `u16`-indexed mesh:
+        let commands = paint(
+            &mut split_painter,
+            &mut backend,
+            vec![mesh(egui::TextureId::default(), 16384)],
+        );
+        assert_eq!(draws(&commands).len(), 2);
+        assert_eq!(
+            count(&commands, |c| matches!(c, Command::BufferUpdate(_))),
+            4
+        );
+
+        // With `u32` indices it is drawn in one go:
+        let mut backend = RecordingBackend::new(Backend::OpenGl);
+        let mut u32_painter = painter(&mut backend);
+        let commands = paint(
+            &mut u32_painter,
+            &mut backend,
+            vec![mesh(egui::TextureId::default(), 16384)],
+        );
+        assert_eq!(draws(&commands), vec![0]);
+    }
+
+    /// The scissor rects for drawing a clipped and an unclipped mesh
+    /// into a 256x128 target of an 800x600 window.
+    fn offscreen_scissors(backend: Backend) -> Vec<Command> {
+        let mut backend = RecordingBackend::new(backend);
+        let mut painter = painter(&mut backend);
+        let mut clipped = mesh(egui::TextureId::default(), 1);
+        clipped.clip_rect =
+            egui::Rect::from_min_max(egui::pos2(10.0, 20.0), egui::pos2(50.0, 60.0));
+        let texture = backend.new_render_texture(miniquad::TextureParams {
+            width: 256,
+            height: 128,
+            ..Default::default()
+        });
+        let pass = backend.new_render_pass(texture, None);
+
+        backend.take_commands();
+        painter.paint(
+            &mut backend,
+            Some(pass),
+            PassAction::Nothing,
+            (256, 128),
+            600.0,
+            None,
+            vec![clipped, mesh(egui::TextureId::default(), 1)],
+            &egui::Context::default(),
+        );
+        backend
+            .take_commands()
+            .into_iter()
+            .filter(|c| matches!(c, Command::ApplyScissorRect { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn opengl_offscreen_scissors_are_flipped_in_the_target() {
+        assert_eq!(
+            offscreen_scissors(Backend::OpenGl),
+            [
+                Command::ApplyScissorRect {
+                    x: 10,
+                    y: 128 - 60,
+                    w: 40,
+                    h: 40
+                },
+                Command::ApplyScissorRect {
+                    x: 0,
+                    y: 0,
+                    w: 256,
+                    h: 128
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn metal_offscreen_scissors_are_flipped_in_the_window() {
+        // miniquad computes `window_height - (y + h)`, which has to land in the target.
+        assert_eq!(
+            offscreen_scissors(Backend::Metal),
+            [
+                Command::ApplyScissorRect {
+                    x: 10,
+                    y: 600 - 60,
+                    w: 40,
+                    h: 40
+                },
+                Command::ApplyScissorRect {
+                    x: 0,
+                    y: 600 - 128,
+                    w: 256,
+                    h: 128
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn u32_indices_depend_on_the_gl_version() {
+        let info = |backend, gl_version_string: &str| miniquad::ContextInfo {
+            backend,
+            gl_version_string: gl_version_string.to_owned(),
+            glsl_support: Default::default(),
+            features: Default::default(),
+        };
+        let table = [
+            (info(Backend::OpenGl, "4.6.0 NVIDIA 535.183.01"), true),
+            (info(Backend::OpenGl, "2.1 Mesa 23.2.1"), true),
+            (info(Backend::OpenGl, ""), true),
+            (info(Backend::OpenGl, "OpenGL ES 3.2 V@0502.0"), true),
+            (info(Backend::OpenGl, "OpenGL ES 2.0 build 1.13"), false),
+            (info(Backend::OpenGl, "WebGL 1.0"), false),
+            (info(Backend::OpenGl, "WebGL 2.0"), false),
+            (info(Backend::Metal, ""), false),
+        ];
+        for (info, u32_indices) in table {
+            assert_eq!(
+                supports_u32_indices(&info),
+                u32_indices,
+                "{:?} {:?}",
+                info.backend,
+                info.gl_version_string
+            );
+        }
+    }
+
+    #[test]
+    fn gles2_splits_large_meshes() {
+        let mut backend = RecordingBackend::new(Backend::OpenGl);
+        backend.gl_version_string = "OpenGL ES 2.0".to_owned();
+        let mut painter = painter(&mut backend);
+        let commands = backend.take_commands();
+        assert!(commands.iter().any(|c| matches!(
+            c,
+            Command::NewBuffer {
+                type_: BufferType::IndexBuffer,
+                element_size: Some(2),
+                ..
+            }
+        )));
+
+        let commands = paint(
+            &mut painter,
+            &mut backend,
+            vec![mesh(egui::TextureId::default(), 16384)],
+        );
+        assert_eq!(draws(&commands).len(), 2);
+    }
+
+    #[test]
+    fn viewport_moves_and_clips_the_meshes() {
+        let mut backend = RecordingBackend::new(Backend::OpenGl);
+        let mut painter = painter(&mut backend);
+        let mut clipped = mesh(egui::TextureId::default(), 1);
+        clipped.clip_rect = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(50.0, 20.0));
+        let viewport = egui::Rect::from_min_size(egui::pos2(100.0, 50.0), egui::vec2(200.0, 100.0));
+
+        backend.take_commands();
+        painter.paint(
+            &mut backend,
+            None,
+            PassAction::Nothing,
+            (800, 600),
+            600.0,
+            Some(viewport),
+            vec![clipped, mesh(egui::TextureId::default(), 1)],
+            &egui::Context::default(),
+        );
+
+        let core = painter.shared.0.borrow();
+        assert_eq!(core.vertices[0].pos, egui::pos2(100.0, 50.0));
+        assert_eq!(core.vertices[4].pos, egui::pos2(100.0, 50.0));
+        let scissors: Vec<_> = backend
+            .take_commands()
+            .into_iter()
+            .filter(|c| matches!(c, Command::ApplyScissorRect { .. }))
+            .collect();
+        assert_eq!(
+            scissors,
+            [
+                Command::ApplyScissorRect {
+                    x: 100,
+                    y: 600 - 70,
+                    w: 50,
+                    h: 20
+                },
+                // Unclipped meshes are clipped to the viewport:
+                Command::ApplyScissorRect {
+                    x: 100,
+                    y: 600 - 150,
+                    w: 200,
+                    h: 100
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn painter_in_use_is_skipped() {
+        let mut backend = RecordingBackend::new(Backend::OpenGl);
+        let mut painter = painter(&mut backend);
+        let shared = painter.shared.clone();
+        let _busy = shared.0.borrow_mut();
+
+        let commands = paint(
+            &mut painter,
+            &mut backend,
+            vec![mesh(egui::TextureId::default(), 1)],
+        );
+        assert_eq!(commands, []);
+    }
+}
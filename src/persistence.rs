@@ -0,0 +1,257 @@
+//! Save [`egui::Memory`] (window positions, collapsed headers etc.) between runs.
+//!
+//! See [`crate::EguiMq::new_with_storage`].
+
+/// The key [`crate::EguiMq`] stores [`egui::Memory`] under.
+pub const EGUI_MEMORY_KEY: &str = "egui_memory";
+
+/// Key-value storage that survives restarts.
+///
+/// Use [`FileStorage`] on native and [`LocalStorage`] on the web, or implement your own.
+pub trait Storage {
+    fn get_string(&self, key: &str) -> Option<String>;
+
+    fn set_string(&mut self, key: &str, value: String);
+
+    /// Write what has been set to disk, if the storage doesn't do that right away.
+    fn flush(&mut self) {}
+}
+
+/// Stores everything in one [RON](https://github.com/ron-rs/ron) file.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage {
+    path: std::path::PathBuf,
+    values: std::collections::HashMap<String, String>,
+    dirty: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    /// Loads `path` if it exists. It is created on the first [`Storage::flush`].
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        let path = path.into();
+        let values = match std::fs::read_to_string(&path) {
+            Ok(contents) => match ron::from_str(&contents) {
+                Ok(values) => values,
+                Err(err) => {
+                    eprintln!("Failed to parse {}: {}", path.display(), err);
+                    Default::default()
+                }
+            },
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    eprintln!("Failed to read {}: {}", path.display(), err);
+                }
+                Default::default()
+            }
+        };
+        Self {
+            path,
+            values,
+            dirty: false,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FileStorage {
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        if self.values.get(key) != Some(&value) {
+            self.values.insert(key.to_owned(), value);
+            self.dirty = true;
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        if let Some(dir) = self.path.parent() {
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                eprintln!("Failed to create {}: {}", dir.display(), err);
+                return;
+            }
+        }
+        let result = ron::ser::to_string_pretty(&self.values, Default::default())
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                std::fs::write(&self.path, contents).map_err(|err| err.to_string())
+            });
+        match result {
+            Ok(()) => self.dirty = false,
+            Err(err) => eprintln!("Failed to write {}: {}", self.path.display(), err),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    fn egui_miniquad_storage_get(key: sapp_jsutils::JsObjectWeak) -> sapp_jsutils::JsObject;
+    fn egui_miniquad_storage_set(
+        key: sapp_jsutils::JsObjectWeak,
+        value: sapp_jsutils::JsObjectWeak,
+    );
+}
+
+/// The browser's `localStorage`.
+///
+/// Needs `sapp_jsutils.js` and `egui_miniquad_storage.js` (found in `docs/`)
+/// to be loaded after `gl.js` in your `index.html`.
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+pub struct LocalStorage {}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for LocalStorage {
+    fn get_string(&self, key: &str) -> Option<String> {
+        let key = sapp_jsutils::JsObject::string(key);
+        let value = unsafe { egui_miniquad_storage_get(key.weak()) };
+        if value.is_nil() {
+            None
+        } else {
+            let mut buf = String::new();
+            value.to_string(&mut buf);
+            Some(buf)
+        }
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        let key = sapp_jsutils::JsObject::string(key);
+        let value = sapp_jsutils::JsObject::string(&value);
+        unsafe { egui_miniquad_storage_set(key.weak(), value.weak()) };
+    }
+}
+
+pub(crate) fn load_memory(storage: &dyn Storage) -> Option<egui::Memory> {
+    let ron = storage.get_string(EGUI_MEMORY_KEY)?;
+    match ron::from_str(&ron) {
+        Ok(memory) => Some(memory),
+        Err(err) => {
+            eprintln!("Failed to restore egui memory: {}", err);
+            None
+        }
+    }
+}
+
+pub(crate) fn save_memory(storage: &mut dyn Storage, egui_ctx: &egui::Context) {
+    match egui_ctx.memory(ron::to_string) {
+        Ok(ron) => {
+            storage.set_string(EGUI_MEMORY_KEY, ron);
+            storage.flush();
+        }
+        Err(err) => eprintln!("Failed to save egui memory: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+    use super::*;
+    use crate::headless::{run_frame, HeadlessPlatform, RecordingBackend};
+
+    #[derive(Clone, Default)]
+    struct MemoryStorage(Rc<RefCell<(HashMap<String, String>, usize)>>);
+
+    impl MemoryStorage {
+        fn flushes(&self) -> usize {
+            self.0.borrow().1
+        }
+    }
+
+    impl Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.borrow().0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.borrow_mut().0.insert(key.to_owned(), value);
+        }
+
+        fn flush(&mut self) {
+            self.0.borrow_mut().1 += 1;
+        }
+    }
+
+    fn egui_mq(
+        backend: &mut RecordingBackend,
+        storage: &MemoryStorage,
+    ) -> (Rc<RefCell<HeadlessPlatform>>, crate::EguiMq) {
+        let platform = Rc::new(RefCell::new(HeadlessPlatform::new(800.0, 600.0)));
+        let egui_mq = crate::EguiMqBuilder::new()
+            .platform(platform.clone())
+            .storage(storage.clone())
+            .build(backend);
+        (platform, egui_mq)
+    }
+
+    #[test]
+    fn memory_is_restored() {
+        let id = egui::Id::new("answer");
+        let egui_ctx = egui::Context::default();
+        egui_ctx.memory_mut(|memory| memory.data.insert_persisted(id, 42));
+        let mut storage = MemoryStorage::default();
+        save_memory(&mut storage, &egui_ctx);
+
+        let mut backend = RecordingBackend::new(miniquad::Backend::OpenGl);
+        let (_platform, egui_mq) = egui_mq(&mut backend, &storage);
+        let answer = egui_mq
+            .egui_ctx()
+            .memory_mut(|memory| memory.data.get_persisted::<i32>(id));
+        assert_eq!(answer, Some(42));
+    }
+
+    #[test]
+    fn saves_after_the_interval_and_when_dropped() {
+        let storage = MemoryStorage::default();
+        let mut backend = RecordingBackend::new(miniquad::Backend::OpenGl);
+        let (platform, mut egui_mq) = egui_mq(&mut backend, &storage);
+        egui_mq.set_auto_save_interval(std::time::Duration::from_secs(10));
+
+        run_frame(&mut egui_mq, &mut backend, |_| {});
+        platform.borrow_mut().time = 9.0;
+        run_frame(&mut egui_mq, &mut backend, |_| {});
+        assert_eq!(storage.flushes(), 0);
+
+        platform.borrow_mut().time = 10.0;
+        run_frame(&mut egui_mq, &mut backend, |_| {});
+        assert_eq!(storage.flushes(), 1);
+        assert!(storage.get_string(EGUI_MEMORY_KEY).is_some());
+
+        drop(egui_mq);
+        assert_eq!(storage.flushes(), 2);
+    }
+
+    #[test]
+    fn stage_saves_when_asked_to_quit() {
+        use miniquad::EventHandler as _;
+
+        struct App;
+
+        impl crate::EguiApp for App {
+            fn ui(&mut self, _mq_ctx: &mut dyn miniquad::RenderingBackend, _: &egui::Context) {}
+        }
+
+        let storage = MemoryStorage::default();
+        let mut backend = RecordingBackend::new(miniquad::Backend::OpenGl);
+        let (_platform, egui_mq) = egui_mq(&mut backend, &storage);
+        let mut stage = crate::EguiStage::from_parts(Box::new(backend), egui_mq, App);
+        stage.draw();
+        assert_eq!(storage.flushes(), 0);
+
+        stage.quit_requested_event();
+        assert_eq!(storage.flushes(), 1);
+        assert!(storage.get_string(EGUI_MEMORY_KEY).is_some());
+    }
+
+    #[test]
+    fn malformed_memory_is_ignored() {
+        let mut storage = MemoryStorage::default();
+        storage.set_string(EGUI_MEMORY_KEY, "(not: ron".to_owned());
+        assert!(load_memory(&storage).is_none());
+    }
+}
@@ -0,0 +1,185 @@
+use miniquad as mq;
+
+/// The window and platform services used by [`crate::EguiMq`].
+///
+/// [`MiniquadPlatform`] forwards everything to the miniquad window.
+/// Implement this yourself to run egui without a window, e.g. in tests
+/// (see also `headless::HeadlessPlatform`, behind the `headless` feature).
+pub trait Platform {
+    /// See [`miniquad::window::dpi_scale`].
+    fn dpi_scale(&self) -> f32;
+
+    /// Size of the window in physical pixels. See [`miniquad::window::screen_size`].
+    fn screen_size(&self) -> (f32, f32);
+
+    /// Seconds since some fixed point in time. See [`miniquad::date::now`].
+    fn now(&self) -> f64;
+
+    fn show_mouse(&mut self, shown: bool);
+
+    fn set_mouse_cursor(&mut self, cursor_icon: mq::CursorIcon);
+
+    fn open_url(&mut self, url: &str, new_tab: bool);
+
+    /// See [`miniquad::window::schedule_update`].
+    fn schedule_update(&mut self);
+
+    /// See [`miniquad::window::set_fullscreen`].
+    fn set_fullscreen(&mut self, fullscreen: bool);
+
+    /// In physical pixels. See [`miniquad::window::set_window_size`].
+    fn set_window_size(&mut self, width: u32, height: u32);
+
+    /// See [`miniquad::window::set_cursor_grab`].
+    fn set_cursor_grab(&mut self, grab: bool);
+
+    /// See [`miniquad::window::request_quit`].
+    fn request_quit(&mut self);
+
+    /// See [`miniquad::window::cancel_quit`].
+    fn cancel_quit(&mut self);
+
+    /// See [`miniquad::window::dropped_file_count`].
+    fn dropped_file_count(&self) -> usize;
+
+    /// See [`miniquad::window::dropped_file_path`].
+    fn dropped_file_path(&self, index: usize) -> Option<std::path::PathBuf>;
+
+    /// See [`miniquad::window::dropped_file_bytes`].
+    fn dropped_file_bytes(&self, index: usize) -> Option<Vec<u8>>;
+}
+
+/// Shared ownership, so the platform can be inspected while [`crate::EguiMq`] uses it.
+impl<P: Platform> Platform for std::rc::Rc<std::cell::RefCell<P>> {
+    fn dpi_scale(&self) -> f32 {
+        self.borrow().dpi_scale()
+    }
+
+    fn screen_size(&self) -> (f32, f32) {
+        self.borrow().screen_size()
+    }
+
+    fn now(&self) -> f64 {
+        self.borrow().now()
+    }
+
+    fn show_mouse(&mut self, shown: bool) {
+        self.borrow_mut().show_mouse(shown);
+    }
+
+    fn set_mouse_cursor(&mut self, cursor_icon: mq::CursorIcon) {
+        self.borrow_mut().set_mouse_cursor(cursor_icon);
+    }
+
+    fn open_url(&mut self, url: &str, new_tab: bool) {
+        self.borrow_mut().open_url(url, new_tab);
+    }
+
+    fn schedule_update(&mut self) {
+        self.borrow_mut().schedule_update();
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.borrow_mut().set_fullscreen(fullscreen);
+    }
+
+    fn set_window_size(&mut self, width: u32, height: u32) {
+        self.borrow_mut().set_window_size(width, height);
+    }
+
+    fn set_cursor_grab(&mut self, grab: bool) {
+        self.borrow_mut().set_cursor_grab(grab);
+    }
+
+    fn request_quit(&mut self) {
+        self.borrow_mut().request_quit();
+    }
+
+    fn cancel_quit(&mut self) {
+        self.borrow_mut().cancel_quit();
+    }
+
+    fn dropped_file_count(&self) -> usize {
+        self.borrow().dropped_file_count()
+    }
+
+    fn dropped_file_path(&self, index: usize) -> Option<std::path::PathBuf> {
+        self.borrow().dropped_file_path(index)
+    }
+
+    fn dropped_file_bytes(&self, index: usize) -> Option<Vec<u8>> {
+        self.borrow().dropped_file_bytes(index)
+    }
+}
+
+/// The miniquad window. This is what [`crate::EguiMq::new`] uses.
+pub struct MiniquadPlatform;
+
+impl MiniquadPlatform {
+    #[allow(clippy::new_without_default)] // there is only one miniquad window
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Platform for MiniquadPlatform {
+    fn dpi_scale(&self) -> f32 {
+        mq::window::dpi_scale()
+    }
+
+    fn screen_size(&self) -> (f32, f32) {
+        mq::window::screen_size()
+    }
+
+    fn now(&self) -> f64 {
+        mq::date::now()
+    }
+
+    fn show_mouse(&mut self, shown: bool) {
+        mq::window::show_mouse(shown);
+    }
+
+    fn set_mouse_cursor(&mut self, cursor_icon: mq::CursorIcon) {
+        mq::window::set_mouse_cursor(cursor_icon);
+    }
+
+    fn open_url(&mut self, url: &str, new_tab: bool) {
+        quad_url::link_open(url, new_tab);
+    }
+
+    fn schedule_update(&mut self) {
+        mq::window::schedule_update();
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        mq::window::set_fullscreen(fullscreen);
+    }
+
+    fn set_window_size(&mut self, width: u32, height: u32) {
+        mq::window::set_window_size(width, height);
+    }
+
+    fn set_cursor_grab(&mut self, grab: bool) {
+        mq::window::set_cursor_grab(grab);
+    }
+
+    fn request_quit(&mut self) {
+        mq::window::request_quit();
+    }
+
+    fn cancel_quit(&mut self) {
+        mq::window::cancel_quit();
+    }
+
+    fn dropped_file_count(&self) -> usize {
+        mq::window::dropped_file_count()
+    }
+
+    fn dropped_file_path(&self, index: usize) -> Option<std::path::PathBuf> {
+        mq::window::dropped_file_path(index)
+    }
+
+    fn dropped_file_bytes(&self, index: usize) -> Option<Vec<u8>> {
+        mq::window::dropped_file_bytes(index)
+    }
+}
@@ -0,0 +1,464 @@
+//! Draw egui on the CPU, for golden-image tests of the painter without a GPU.
+//!
+//! [`SoftwareRasterizer`] draws the same meshes as the painter, the same way:
+//! premultiplied alpha blending (`One, OneMinusSrcAlpha`) in gamma space,
+//! clip rects rounded to whole pixels and clamped texture sampling.
+//! Use it with [`crate::EguiMq::draw_to_image`] and compare the result with [`compare_to_golden`].
+//!
+//! ```no_run
+//! use std::{cell::RefCell, rc::Rc};
+//! use egui_miniquad::{headless, rasterizer};
+//!
+//! let mut backend = headless::RecordingBackend::new(miniquad::Backend::OpenGl);
+//! let platform = Rc::new(RefCell::new(headless::HeadlessPlatform::new(320.0, 240.0)));
+//! let mut egui_mq = egui_miniquad::EguiMq::with_platform(&mut backend, platform);
+//! let mut rasterizer = rasterizer::SoftwareRasterizer::default();
+//!
+//! egui_mq.run(&mut backend, |_mq_ctx, egui_ctx| {
+//!     egui::CentralPanel::default().show(egui_ctx, |ui| {
+//!         ui.label("Hello World!");
+//!     });
+//! });
+//! let image = egui_mq.draw_to_image(&mut rasterizer);
+//!
+//! rasterizer::compare_to_golden(&image, "tests/goldens/hello.png", 2).unwrap();
+//! ```
+
+use std::{collections::HashMap, path::Path};
+
+use egui::{epaint::Vertex, Color32, ColorImage, Pos2, Rect, TextureFilter, TextureId};
+
+use crate::PainterOptions;
+
+struct Texture {
+    image: ColorImage,
+    filter: TextureFilter,
+}
+
+/// Draws [`egui::ClippedPrimitive`]s into a [`ColorImage`].
+///
+/// Keeps its own copy of egui's textures, like the painter does on the GPU.
+#[derive(Default)]
+pub struct SoftwareRasterizer {
+    options: PainterOptions,
+    textures: HashMap<TextureId, Texture>,
+}
+
+impl SoftwareRasterizer {
+    /// Draw like a painter with these options,
+    /// i.e. the ones given to [`crate::EguiMqBuilder::painter_options`].
+    pub fn new(options: PainterOptions) -> Self {
+        Self {
+            options,
+            textures: HashMap::new(),
+        }
+    }
+
+    /// Make a [`egui::TextureId::User`] texture available for drawing.
+    pub fn set_user_texture(&mut self, id: u64, image: ColorImage, filter: TextureFilter) {
+        self.textures
+            .insert(TextureId::User(id), Texture { image, filter });
+    }
+
+    pub fn set_texture(&mut self, tex_id: TextureId, delta: &egui::epaint::ImageDelta) {
+        let image = match &delta.image {
+            egui::ImageData::Color(image) => (**image).clone(),
+            egui::ImageData::Font(image) => ColorImage {
+                size: image.size,
+                pixels: image.srgba_pixels(None).collect(),
+            },
+        };
+        // The painter uses the magnification filter for minification too:
+        let filter = self
+            .options
+            .texture_filter
+            .unwrap_or(delta.options.magnification);
+
+        if let Some([x, y]) = delta.pos {
+            // Partial update
+            if let Some(texture) = self.textures.get_mut(&tex_id) {
+                let width = texture.image.width();
+                for row in 0..image.height() {
+                    let dst = (y + row) * width + x;
+                    let src = row * image.width();
+                    texture.image.pixels[dst..dst + image.width()]
+                        .copy_from_slice(&image.pixels[src..src + image.width()]);
+                }
+            } else {
+                eprintln!("Failed to find egui texture {tex_id:?}");
+            }
+        } else {
+            self.textures.insert(tex_id, Texture { image, filter });
+        }
+    }
+
+    pub fn free_texture(&mut self, tex_id: TextureId) {
+        self.textures.remove(&tex_id);
+    }
+
+    /// Update the textures and draw the primitives into a transparent image
+    /// of `size_in_pixels`.
+    pub fn paint_and_update_textures(
+        &mut self,
+        size_in_pixels: [usize; 2],
+        primitives: &[egui::ClippedPrimitive],
+        textures_delta: &egui::TexturesDelta,
+        pixels_per_point: f32,
+    ) -> ColorImage {
+        for (id, image_delta) in &textures_delta.set {
+            self.set_texture(*id, image_delta);
+        }
+
+        let mut image = ColorImage::new(size_in_pixels, Color32::TRANSPARENT);
+        self.paint(&mut image, primitives, pixels_per_point);
+
+        for &id in &textures_delta.free {
+            self.free_texture(id);
+        }
+
+        image
+    }
+
+    /// Draw the primitives on top of `image`.
+    ///
+    /// [`egui::epaint::Primitive::Callback`]s are skipped, since they draw with a GPU.
+    pub fn paint(
+        &self,
+        image: &mut ColorImage,
+        primitives: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+    ) {
+        for egui::ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in primitives
+        {
+            let mesh = match primitive {
+                egui::epaint::Primitive::Mesh(mesh) => mesh,
+                egui::epaint::Primitive::Callback(_) => continue,
+            };
+            let texture = if let Some(texture) = self.textures.get(&mesh.texture_id) {
+                texture
+            } else {
+                eprintln!("Texture {:?} not found", mesh.texture_id);
+                continue;
+            };
+
+            let scissor = scissor_rect(image.size, pixels_per_point, *clip_rect);
+            for triangle in mesh.indices.chunks_exact(3) {
+                let vertices = [triangle[0], triangle[1], triangle[2]].map(|index| {
+                    let vertex = mesh.vertices[index as usize];
+                    Vertex {
+                        pos: (vertex.pos.to_vec2() * pixels_per_point).to_pos2(),
+                        ..vertex
+                    }
+                });
+                draw_triangle(image, scissor, texture, &vertices);
+            }
+        }
+    }
+}
+
+/// The clip rect in whole pixels, rounded the same way as the painter's scissor rect.
+fn scissor_rect(size_in_pixels: [usize; 2], pixels_per_point: f32, clip_rect: Rect) -> [usize; 4] {
+    let (width, height) = (size_in_pixels[0] as f32, size_in_pixels[1] as f32);
+    let min_x = (pixels_per_point * clip_rect.min.x).clamp(0.0, width);
+    let min_y = (pixels_per_point * clip_rect.min.y).clamp(0.0, height);
+    let max_x = (pixels_per_point * clip_rect.max.x).clamp(min_x, width);
+    let max_y = (pixels_per_point * clip_rect.max.y).clamp(min_y, height);
+    [min_x, min_y, max_x, max_y].map(|value| value.round() as usize)
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`.
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn draw_triangle(
+    image: &mut ColorImage,
+    [min_x, min_y, max_x, max_y]: [usize; 4],
+    texture: &Texture,
+    vertices: &[Vertex; 3],
+) {
+    let [mut a, mut b, c] = *vertices;
+    let mut area = edge(a.pos, b.pos, c.pos);
+    if area == 0.0 {
+        return;
+    }
+    if area < 0.0 {
+        std::mem::swap(&mut a, &mut b);
+        area = -area;
+    }
+
+    let bounds = Rect::from_points(&[a.pos, b.pos, c.pos]);
+    let x_range = (bounds.min.x.floor().max(min_x as f32) as usize)
+        ..(bounds.max.x.ceil().min(max_x as f32) as usize);
+    let y_range = (bounds.min.y.floor().max(min_y as f32) as usize)
+        ..(bounds.max.y.ceil().min(max_y as f32) as usize);
+
+    // Top-left fill rule, so pixels on edges shared by two triangles are only drawn once.
+    // With y pointing down and clockwise winding, top edges go right and left edges go up.
+    let is_top_left = |from: Pos2, to: Pos2| (from.y == to.y && to.x > from.x) || to.y < from.y;
+    let covers = |w: f32, from: Pos2, to: Pos2| w > 0.0 || (w == 0.0 && is_top_left(from, to));
+
+    let width = image.width();
+    for y in y_range {
+        for x in x_range.clone() {
+            // Sample at the pixel center, like the GPU:
+            let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let w_a = edge(b.pos, c.pos, p);
+            let w_b = edge(c.pos, a.pos, p);
+            let w_c = edge(a.pos, b.pos, p);
+            if !covers(w_a, b.pos, c.pos)
+                || !covers(w_b, c.pos, a.pos)
+                || !covers(w_c, a.pos, b.pos)
+            {
+                continue;
+            }
+
+            let (w_a, w_b, w_c) = (w_a / area, w_b / area, w_c / area);
+            let interpolate = |a: f32, b: f32, c: f32| w_a * a + w_b * b + w_c * c;
+
+            let uv = egui::pos2(
+                interpolate(a.uv.x, b.uv.x, c.uv.x),
+                interpolate(a.uv.y, b.uv.y, c.uv.y),
+            );
+            let texel = sample(texture, uv);
+
+            // `v_rgba_in_gamma * texture_in_gamma`
+            let mut src = texel;
+            for (i, src) in src.iter_mut().enumerate() {
+                let color = interpolate(a.color[i] as f32, b.color[i] as f32, c.color[i] as f32);
+                *src *= color / 255.0;
+            }
+
+            // `One, OneMinusSrcAlpha`
+            let pixel = &mut image.pixels[y * width + x];
+            let mut blended = pixel.to_array();
+            for (i, dst) in blended.iter_mut().enumerate() {
+                let value = src[i] + *dst as f32 / 255.0 * (1.0 - src[3]);
+                *dst = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+            *pixel =
+                Color32::from_rgba_premultiplied(blended[0], blended[1], blended[2], blended[3]);
+        }
+    }
+}
+
+/// Sample the texture at `uv` with clamp-to-edge wrapping, returning RGBA in `0..=1`.
+fn sample(texture: &Texture, uv: Pos2) -> [f32; 4] {
+    let image = &texture.image;
+    let [width, height] = image.size;
+    if width == 0 || height == 0 {
+        return [0.0; 4];
+    }
+    let texel = |x: isize, y: isize| -> [f32; 4] {
+        let x = x.clamp(0, width as isize - 1) as usize;
+        let y = y.clamp(0, height as isize - 1) as usize;
+        image.pixels[y * width + x]
+            .to_array()
+            .map(|channel| channel as f32 / 255.0)
+    };
+
+    let x = uv.x * width as f32;
+    let y = uv.y * height as f32;
+    match texture.filter {
+        TextureFilter::Nearest => texel(x.floor() as isize, y.floor() as isize),
+        TextureFilter::Linear => {
+            // Texel centers are at half-integer coordinates:
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (x0, y0) = (x.floor(), y.floor());
+            let (tx, ty) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as isize, y0 as isize);
+            let [top_left, top_right, bottom_left, bottom_right] = [
+                texel(x0, y0),
+                texel(x0 + 1, y0),
+                texel(x0, y0 + 1),
+                texel(x0 + 1, y0 + 1),
+            ];
+            let mut rgba = [0.0; 4];
+            for (i, rgba) in rgba.iter_mut().enumerate() {
+                let top = top_left[i] + (top_right[i] - top_left[i]) * tx;
+                let bottom = bottom_left[i] + (bottom_right[i] - bottom_left[i]) * tx;
+                *rgba = top + (bottom - top) * ty;
+            }
+            rgba
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Why an image didn't match its golden image.
+#[derive(Debug)]
+pub enum GoldenError {
+    Io(std::io::Error),
+    Decoding(png::DecodingError),
+    Encoding(png::EncodingError),
+    /// There is no golden image yet. Run with `UPDATE_GOLDENS=1` to create it.
+    Missing(std::path::PathBuf),
+    /// The image was written next to the golden as `<name>.new.png`.
+    SizeMismatch {
+        golden: [usize; 2],
+        actual: [usize; 2],
+    },
+    /// The image was written next to the golden as `<name>.new.png`.
+    PixelMismatch {
+        /// Number of pixels where a channel differs by more than the tolerance.
+        count: usize,
+        /// The largest difference of any channel.
+        max_difference: u8,
+    },
+}
+
+impl std::fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+            Self::Decoding(err) => write!(f, "Failed to decode golden image: {err}"),
+            Self::Encoding(err) => write!(f, "Failed to encode image: {err}"),
+            Self::Missing(path) => write!(
+                f,
+                "Missing golden image {}. Run with UPDATE_GOLDENS=1 to create it.",
+                path.display()
+            ),
+            Self::SizeMismatch { golden, actual } => write!(
+                f,
+                "Image is {}x{}, but the golden image is {}x{}",
+                actual[0], actual[1], golden[0], golden[1]
+            ),
+            Self::PixelMismatch {
+                count,
+                max_difference,
+            } => write!(
+                f,
+                "{count} pixels differ from the golden image, by up to {max_difference}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<std::io::Error> for GoldenError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<png::DecodingError> for GoldenError {
+    fn from(err: png::DecodingError) -> Self {
+        Self::Decoding(err)
+    }
+}
+
+impl From<png::EncodingError> for GoldenError {
+    fn from(err: png::EncodingError) -> Self {
+        Self::Encoding(err)
+    }
+}
+
+/// Compare `image` with the PNG at `path`, allowing every channel to be off by `tolerance`.
+///
+/// If the `UPDATE_GOLDENS` environment variable is set, `path` is overwritten with `image` instead.
+/// On a mismatch, `image` is written next to the golden as `<name>.new.png` for inspection.
+pub fn compare_to_golden(
+    image: &ColorImage,
+    path: impl AsRef<Path>,
+    tolerance: u8,
+) -> Result<(), GoldenError> {
+    let path = path.as_ref();
+
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, encode_png(image)?)?;
+        return Ok(());
+    }
+
+    let golden = match std::fs::read(path) {
+        Ok(bytes) => decode_png(&bytes)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(GoldenError::Missing(path.to_owned()));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let result = if golden.size != image.size {
+        Err(GoldenError::SizeMismatch {
+            golden: golden.size,
+            actual: image.size,
+        })
+    } else {
+        let differences = golden.pixels.iter().zip(&image.pixels).map(|(a, b)| {
+            (0..4)
+                .map(|i| a[i].abs_diff(b[i]))
+                .max()
+                .unwrap_or_default()
+        });
+        let (count, max_difference) =
+            differences.fold((0, 0), |(count, max_difference), difference| {
+                (
+                    count + usize::from(difference > tolerance),
+                    max_difference.max(difference),
+                )
+            });
+        if count == 0 {
+            Ok(())
+        } else {
+            Err(GoldenError::PixelMismatch {
+                count,
+                max_difference,
+            })
+        }
+    };
+
+    if result.is_err() {
+        std::fs::write(path.with_extension("new.png"), encode_png(image)?)?;
+    }
+    result
+}
+
+/// Encode the image as an 8-bit RGBA PNG.
+///
+/// The pixels are stored as they are, i.e. with premultiplied alpha.
+pub fn encode_png(image: &ColorImage) -> Result<Vec<u8>, png::EncodingError> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, image.width() as u32, image.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(bytemuck::cast_slice(&image.pixels))?;
+    writer.finish()?;
+    Ok(bytes)
+}
+
+/// Decode an 8-bit RGBA PNG written by [`encode_png`].
+pub fn decode_png(bytes: &[u8]) -> Result<ColorImage, png::DecodingError> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let size = [info.width as usize, info.height as usize];
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer[..info.buffer_size()]
+            .chunks_exact(4)
+            .map(|p| Color32::from_rgba_premultiplied(p[0], p[1], p[2], p[3]))
+            .collect(),
+        png::ColorType::Rgb => buffer[..info.buffer_size()]
+            .chunks_exact(3)
+            .map(|p| Color32::from_rgb(p[0], p[1], p[2]))
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer[..info.buffer_size()]
+            .chunks_exact(2)
+            .map(|p| Color32::from_rgba_premultiplied(p[0], p[0], p[0], p[1]))
+            .collect(),
+        png::ColorType::Grayscale | png::ColorType::Indexed => buffer[..info.buffer_size()]
+            .iter()
+            .map(|&p| Color32::from_gray(p))
+            .collect(),
+    };
+    Ok(ColorImage { size, pixels })
+}
@@ -0,0 +1,259 @@
+use miniquad as mq;
+
+use crate::EguiMq;
+
+/// Your app, run by an [`EguiStage`].
+///
+/// Only [`Self::ui`] is required.
+pub trait EguiApp {
+    /// Called from [`miniquad::EventHandler::update`].
+    ///
+    /// `egui_mq` is there so you can e.g. check [`EguiMq::wants_pointer_input`].
+    fn update(&mut self, _mq_ctx: &mut dyn mq::RenderingBackend, _egui_mq: &mut EguiMq) {}
+
+    /// Show your egui windows, panels etc.
+    fn ui(&mut self, mq_ctx: &mut dyn mq::RenderingBackend, egui_ctx: &egui::Context);
+
+    /// Draw things behind egui. This is where you clear the screen.
+    fn draw_background(&mut self, _mq_ctx: &mut dyn mq::RenderingBackend) {}
+
+    /// Draw things in front of egui.
+    fn draw_foreground(&mut self, _mq_ctx: &mut dyn mq::RenderingBackend) {}
+
+    /// The window was resized, in physical pixels.
+    fn resize_event(&mut self, _width: f32, _height: f32) {}
+
+    /// See [`miniquad::EventHandler::quit_requested_event`].
+    ///
+    /// To cancel the quit, send [`egui::ViewportCommand::CancelClose`] from [`Self::ui`]
+    /// when [`egui::ViewportInfo::close_requested`] is true.
+    fn quit_requested_event(&mut self) {}
+}
+
+/// A [`miniquad::EventHandler`] that forwards all events to [`EguiMq`] and runs your [`EguiApp`].
+///
+/// ```no_run
+/// use miniquad as mq;
+///
+/// struct App;
+///
+/// impl egui_miniquad::EguiApp for App {
+///     fn ui(&mut self, _mq_ctx: &mut dyn mq::RenderingBackend, egui_ctx: &egui::Context) {
+///         egui::Window::new("Egui Window").show(egui_ctx, |ui| {
+///             ui.heading("Hello World!");
+///         });
+///     }
+///
+///     fn draw_background(&mut self, mq_ctx: &mut dyn mq::RenderingBackend) {
+///         mq_ctx.begin_default_pass(mq::PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
+///         mq_ctx.end_render_pass();
+///     }
+/// }
+///
+/// mq::start(mq::conf::Conf::default(), || {
+///     Box::new(egui_miniquad::EguiStage::new(|_mq_ctx, _egui_ctx| App))
+/// });
+/// ```
+pub struct EguiStage<T: EguiApp> {
+    mq_ctx: Box<dyn mq::RenderingBackend>,
+    egui_mq: EguiMq,
+    app: T,
+}
+
+impl<T: EguiApp> EguiStage<T> {
+    /// Creates the rendering backend and an [`EguiMq`] for the miniquad window,
+    /// then your app with `make_app`.
+    ///
+    /// Touches also move the egui pointer, see [`EguiMq::set_touch_emulates_pointer`].
+    pub fn new(make_app: impl FnOnce(&mut dyn mq::RenderingBackend, &egui::Context) -> T) -> Self {
+        let mut mq_ctx = mq::window::new_rendering_backend();
+        let mut egui_mq = EguiMq::new(&mut *mq_ctx);
+        egui_mq.set_touch_emulates_pointer(true);
+        let app = make_app(&mut *mq_ctx, egui_mq.egui_ctx());
+        Self::from_parts(mq_ctx, egui_mq, app)
+    }
+
+    /// Use an [`EguiMq`] you have set up yourself, e.g. with [`crate::EguiMqBuilder`].
+    pub fn from_parts(mq_ctx: Box<dyn mq::RenderingBackend>, egui_mq: EguiMq, app: T) -> Self {
+        Self {
+            mq_ctx,
+            egui_mq,
+            app,
+        }
+    }
+
+    pub fn app(&self) -> &T {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut T {
+        &mut self.app
+    }
+
+    pub fn egui_mq(&self) -> &EguiMq {
+        &self.egui_mq
+    }
+
+    pub fn egui_mq_mut(&mut self) -> &mut EguiMq {
+        &mut self.egui_mq
+    }
+}
+
+impl<T: EguiApp> mq::EventHandler for EguiStage<T> {
+    fn update(&mut self) {
+        self.app.update(&mut *self.mq_ctx, &mut self.egui_mq);
+    }
+
+    fn draw(&mut self) {
+        let Self {
+            mq_ctx,
+            egui_mq,
+            app,
+        } = self;
+
+        egui_mq.run(&mut **mq_ctx, |mq_ctx, egui_ctx| app.ui(mq_ctx, egui_ctx));
+        app.draw_background(&mut **mq_ctx);
+        egui_mq.draw(&mut **mq_ctx);
+        app.draw_foreground(&mut **mq_ctx);
+        mq_ctx.commit_frame();
+    }
+
+    fn resize_event(&mut self, width: f32, height: f32) {
+        self.egui_mq.resize_event(width, height);
+        self.app.resize_event(width, height);
+    }
+
+    fn mouse_motion_event(&mut self, x: f32, y: f32) {
+        self.egui_mq.mouse_motion_event(x, y);
+    }
+
+    fn mouse_wheel_event(&mut self, dx: f32, dy: f32) {
+        self.egui_mq.mouse_wheel_event(dx, dy);
+    }
+
+    fn mouse_button_down_event(&mut self, mb: mq::MouseButton, x: f32, y: f32) {
+        self.egui_mq.mouse_button_down_event(mb, x, y);
+    }
+
+    fn mouse_button_up_event(&mut self, mb: mq::MouseButton, x: f32, y: f32) {
+        self.egui_mq.mouse_button_up_event(mb, x, y);
+    }
+
+    fn char_event(&mut self, character: char, _keymods: mq::KeyMods, _repeat: bool) {
+        self.egui_mq.char_event(character);
+    }
+
+    fn key_down_event(&mut self, keycode: mq::KeyCode, keymods: mq::KeyMods, repeat: bool) {
+        self.egui_mq.key_down_event(keycode, keymods, repeat);
+    }
+
+    fn key_up_event(&mut self, keycode: mq::KeyCode, keymods: mq::KeyMods) {
+        self.egui_mq.key_up_event(keycode, keymods);
+    }
+
+    fn touch_event(&mut self, phase: mq::TouchPhase, id: u64, x: f32, y: f32) {
+        self.egui_mq.touch_event(phase, id, x, y);
+    }
+
+    fn files_dropped_event(&mut self) {
+        self.egui_mq.files_dropped_event();
+    }
+
+    fn window_minimized_event(&mut self) {
+        self.egui_mq.window_minimized_event();
+    }
+
+    fn window_restored_event(&mut self) {
+        self.egui_mq.window_restored_event();
+    }
+
+    fn quit_requested_event(&mut self) {
+        self.app.quit_requested_event();
+        self.egui_mq.quit_requested_event();
+        #[cfg(feature = "persistence")]
+        self.egui_mq.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use mq::EventHandler as _;
+
+    use super::*;
+    use crate::headless::{HeadlessPlatform, RecordingBackend};
+
+    struct App {
+        events: Vec<egui::Event>,
+        /// [`HeadlessPlatform::scheduled_updates`] when the app was asked to quit.
+        updates_on_quit: Option<usize>,
+        platform: Rc<RefCell<HeadlessPlatform>>,
+    }
+
+    impl EguiApp for App {
+        fn ui(&mut self, _mq_ctx: &mut dyn mq::RenderingBackend, egui_ctx: &egui::Context) {
+            self.events = egui_ctx.input(|i| i.raw.events.clone());
+        }
+
+        fn quit_requested_event(&mut self) {
+            self.updates_on_quit = Some(self.platform.borrow().scheduled_updates);
+        }
+    }
+
+    fn stage() -> EguiStage<App> {
+        let mut backend = RecordingBackend::new(mq::Backend::OpenGl);
+        let platform = Rc::new(RefCell::new(HeadlessPlatform::new(800.0, 600.0)));
+        let egui_mq = EguiMq::with_platform(&mut backend, platform.clone());
+        let app = App {
+            events: Vec::new(),
+            updates_on_quit: None,
+            platform,
+        };
+        EguiStage::from_parts(Box::new(backend), egui_mq, app)
+    }
+
+    #[test]
+    fn input_reaches_egui() {
+        let mut stage = stage();
+        stage.mouse_motion_event(10.0, 20.0);
+        stage.mouse_button_down_event(mq::MouseButton::Left, 10.0, 20.0);
+        stage.key_down_event(mq::KeyCode::A, mq::KeyMods::default(), false);
+        stage.char_event('a', mq::KeyMods::default(), false);
+        stage.draw();
+
+        let events = &stage.app().events;
+        let pos = egui::pos2(10.0, 20.0);
+        assert!(events.contains(&egui::Event::PointerMoved(pos)));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            egui::Event::PointerButton {
+                pos: p,
+                button: egui::PointerButton::Primary,
+                pressed: true,
+                ..
+            } if *p == pos
+        )));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            egui::Event::Key {
+                key: egui::Key::A,
+                pressed: true,
+                ..
+            }
+        )));
+        assert!(events.contains(&egui::Event::Text("a".to_owned())));
+    }
+
+    #[test]
+    fn app_hears_of_quitting_first() {
+        let mut stage = stage();
+        stage.draw();
+        let updates = stage.app().platform.borrow().scheduled_updates;
+
+        stage.quit_requested_event();
+        assert_eq!(stage.app().updates_on_quit, Some(updates));
+        assert!(stage.app().platform.borrow().scheduled_updates > updates);
+        assert!(!stage.app().platform.borrow().quit_requested);
+    }
+}
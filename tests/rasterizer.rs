@@ -0,0 +1,72 @@
+use std::{cell::RefCell, rc::Rc};
+
+use egui_miniquad::{headless, rasterizer, EguiMq, EguiMqBuilder, PainterOptions};
+
+fn egui_mq(backend: &mut headless::RecordingBackend, painter_options: PainterOptions) -> EguiMq {
+    let platform = Rc::new(RefCell::new(headless::HeadlessPlatform::new(320.0, 240.0)));
+    EguiMqBuilder::new()
+        .platform(platform)
+        .painter_options(painter_options)
+        .build(backend)
+}
+
+fn golden(name: &str) -> String {
+    format!("{}/tests/goldens/{name}.png", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn hello_world() {
+    let mut backend = headless::RecordingBackend::new(miniquad::Backend::OpenGl);
+    let mut egui_mq = egui_mq(&mut backend, PainterOptions::default());
+    let mut rasterizer = rasterizer::SoftwareRasterizer::default();
+
+    egui_mq.run(&mut backend, |_mq_ctx, egui_ctx| {
+        egui::CentralPanel::default().show(egui_ctx, |ui| {
+            ui.heading("Hello World!");
+            ui.label("egui ❤ miniquad");
+            let _ = ui.button("Button");
+        });
+    });
+    let image = egui_mq.draw_to_image(&mut rasterizer);
+
+    rasterizer::compare_to_golden(&image, golden("hello_world"), 2).unwrap();
+}
+
+/// Draw a 2x2 checkerboard, which egui asks to filter linearly, scaled up to 64x64 pixels.
+fn draw_checkerboard(texture_filter: Option<egui::TextureFilter>) -> Vec<egui::Color32> {
+    let painter_options = PainterOptions { texture_filter };
+    let mut backend = headless::RecordingBackend::new(miniquad::Backend::OpenGl);
+    let mut egui_mq = egui_mq(&mut backend, painter_options);
+    let mut rasterizer = rasterizer::SoftwareRasterizer::new(painter_options);
+
+    let rect = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(64.0, 64.0));
+    egui_mq.run(&mut backend, |_mq_ctx, egui_ctx| {
+        let mut checkerboard = egui::ColorImage::new([2, 2], egui::Color32::BLACK);
+        checkerboard.pixels[1] = egui::Color32::WHITE;
+        checkerboard.pixels[2] = egui::Color32::WHITE;
+        let texture =
+            egui_ctx.load_texture("checkerboard", checkerboard, egui::TextureOptions::LINEAR);
+        egui_ctx.layer_painter(egui::LayerId::background()).image(
+            texture.id(),
+            rect,
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+    });
+    let image = egui_mq.draw_to_image(&mut rasterizer);
+
+    (0..64)
+        .flat_map(|y| (0..64).map(move |x| (x, y)))
+        .map(|(x, y)| image[(x, y)])
+        .collect()
+}
+
+#[test]
+fn texture_filter_overrides_egui() {
+    let is_gray =
+        |color: &egui::Color32| *color != egui::Color32::BLACK && *color != egui::Color32::WHITE;
+    assert!(draw_checkerboard(None).iter().any(is_gray));
+    assert!(!draw_checkerboard(Some(egui::TextureFilter::Nearest))
+        .iter()
+        .any(is_gray));
+}